@@ -0,0 +1,218 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use crate::{RouteKind, RouteMethodDesc};
+
+/// Routes axum would refuse, or `url_for` couldn't tell apart
+#[derive(Debug,Clone)]
+pub enum RouteConflict {
+    /// several handlers for the same method on the same path, placeholder names aside
+    Route { method: String, path: String, handlers: Vec<RouteMethodDesc> },
+    /// routes axum can't insert side by side: the same path with differently named placeholders whatever their methods,
+    /// or a route taking the path of a nested service
+    Overlap { path: String, handlers: Vec<RouteMethodDesc> },
+    /// several routes with the same `name`
+    Name { name: String, handlers: Vec<RouteMethodDesc> },
+}
+
+impl fmt::Display for RouteConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (what, handlers) = match self {
+            RouteConflict::Route { method, path, handlers } => (format!("{} {}", method.to_uppercase(), path).trim_end().to_string(), handlers),
+            RouteConflict::Overlap { path, handlers } => (format!("path {}", path), handlers),
+            RouteConflict::Name { name, handlers } => (format!("route name `{}`", name), handlers),
+        };
+        write!(f, "{} is registered by ", what)?;
+        let handlers: Vec<String> = handlers.iter()
+            .map(|desc| format!("{} ({})", desc.handler_path(), format!("{} {}", desc.http_methods.join(",").to_uppercase(), desc.path).trim_end()))
+            .collect();
+        write!(f, "{}", handlers.join(", "))
+    }
+}
+
+/// Every conflict found among the registered routes
+#[derive(Debug,Clone)]
+pub struct RouteConflicts(pub Vec<RouteConflict>);
+
+impl fmt::Display for RouteConflicts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} conflicting route(s):", self.0.len())?;
+        for conflict in self.0.iter() {
+            writeln!(f, "  {}", conflict)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for RouteConflicts {}
+
+/// the path as axum's router sees it, `{id}` and `{name}` are the same capture
+pub(crate) fn normalize_path(path: &str) -> String {
+    let mut normalized = String::with_capacity(path.len());
+    let mut chars = path.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                normalized.push_str("{{");
+            },
+            '{' => {
+                let placeholder: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                normalized.push_str(if placeholder.starts_with('*') { "{*}" } else { "{}" });
+            },
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+/// find the routes sharing a method and path, the paths spelled with different placeholders,
+/// the routes nested services overlap, the fallbacks and services of the same router,
+/// and the names given to several routes
+pub fn find_conflicts(descs: &[RouteMethodDesc]) -> Vec<RouteConflict> {
+    let mut by_route: BTreeMap<(String, String), Vec<&RouteMethodDesc>> = BTreeMap::new();
+    let mut by_path: BTreeMap<String, Vec<&RouteMethodDesc>> = BTreeMap::new();
+    let mut by_name: BTreeMap<&str, Vec<&RouteMethodDesc>> = BTreeMap::new();
+    for desc in descs {
+        // a router has one fallback, and a nested service takes every method of its path
+        match desc.kind {
+            RouteKind::Fallback => by_route.entry((String::new(), "fallback".to_string())).or_default().push(desc),
+            RouteKind::Service => by_route.entry((normalize_path(&desc.path), "*".to_string())).or_default().push(desc),
+            RouteKind::Handler | RouteKind::WebSocket | RouteKind::Sse => {
+                by_path.entry(normalize_path(&desc.path)).or_default().push(desc);
+                for method in desc.http_methods.iter() {
+                    by_route.entry((normalize_path(&desc.path), method.to_lowercase())).or_default().push(desc);
+                }
+            },
+        }
+        if let Some(name) = desc.name.as_deref() {
+            by_name.entry(name).or_default().push(desc);
+        }
+    }
+
+    let mut conflicts = vec![];
+    for ((_, method), handlers) in by_route {
+        if handlers.len() > 1 {
+            conflicts.push(RouteConflict::Route {
+                method,
+                path: handlers[0].path.clone(),
+                handlers: handlers.into_iter().cloned().collect(),
+            });
+        }
+    }
+    // axum's router takes a path once, `/x/{id}` and `/x/{name}` can't both be inserted even for different methods
+    for handlers in by_path.values() {
+        let spellings: BTreeSet<&str> = handlers.iter().map(|desc| desc.path.as_str()).collect();
+        if spellings.len() > 1 {
+            conflicts.push(RouteConflict::Overlap {
+                path: handlers[0].path.clone(),
+                handlers: handlers.iter().map(|desc| (*desc).clone()).collect(),
+            });
+        }
+    }
+    // a service nested at `/assets` takes `/assets`, `/assets/` and the wildcard `/assets/{*..}`,
+    // which a placeholder starting the segment under it like `/assets/{id}` can't sit next to,
+    // static routes further down like `/assets/logo` are matched before it
+    for service in descs.iter().filter(|desc| desc.kind == RouteKind::Service) {
+        let prefix = normalize_path(&service.path).trim_end_matches('/').to_string();
+        let takes = |path: &str| path == prefix || path.strip_prefix(&format!("{}/", prefix))
+            .is_some_and(|rest| rest.is_empty() || (rest.starts_with('{') && !rest.starts_with("{{")));
+        let nested: Vec<&RouteMethodDesc> = by_path.iter()
+            .filter(|(path, _)| takes(path))
+            .flat_map(|(_, handlers)| handlers.iter().copied())
+            .collect();
+        if !nested.is_empty() {
+            conflicts.push(RouteConflict::Overlap {
+                path: service.path.clone(),
+                handlers: std::iter::once(service).chain(nested).cloned().collect(),
+            });
+        }
+    }
+    for (name, handlers) in by_name {
+        if handlers.len() > 1 {
+            conflicts.push(RouteConflict::Name {
+                name: name.to_string(),
+                handlers: handlers.into_iter().cloned().collect(),
+            });
+        }
+    }
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(mod_name: &str, method: &str, path: &str) -> RouteMethodDesc {
+        RouteMethodDesc::new(mod_name.to_string(), path.to_string(), method.to_string(), "handler".to_string(), String::new(), String::new(), String::new())
+    }
+
+    #[test]
+    fn same_method_and_path_conflict() {
+        let conflicts = find_conflicts(&[route("a", "get", "/x/{id}"), route("b", "get", "/x/{id}")]);
+        assert!(matches!(conflicts.as_slice(), [RouteConflict::Route { method, .. }] if method == "get"));
+    }
+
+    #[test]
+    fn different_methods_of_one_path_dont_conflict() {
+        assert!(find_conflicts(&[route("a", "get", "/x/{id}"), route("b", "post", "/x/{id}")]).is_empty());
+    }
+
+    #[test]
+    fn placeholder_names_conflict_across_methods() {
+        let conflicts = find_conflicts(&[route("a", "get", "/x/{id}"), route("b", "post", "/x/{name}")]);
+        assert!(matches!(conflicts.as_slice(), [RouteConflict::Overlap { handlers, .. }] if handlers.len() == 2));
+    }
+
+    #[test]
+    fn wildcard_names_conflict_across_methods() {
+        let conflicts = find_conflicts(&[route("a", "get", "/files/{*rest}"), route("b", "put", "/files/{*path}")]);
+        assert_eq!(conflicts.len(), 1);
+    }
+
+    fn service(mod_name: &str, path: &str) -> RouteMethodDesc {
+        route(mod_name, "any", path).with_kind(RouteKind::Service)
+    }
+
+    #[test]
+    fn routes_taking_the_path_of_a_service_conflict() {
+        let conflicts = find_conflicts(&[service("a", "/assets"), route("b", "get", "/assets/{*rest}")]);
+        assert!(matches!(conflicts.as_slice(), [RouteConflict::Overlap { path, handlers }] if path == "/assets" && handlers.len() == 2));
+        assert_eq!(find_conflicts(&[service("a", "/assets/"), route("b", "get", "/assets")]).len(), 1);
+        assert_eq!(find_conflicts(&[service("a", "/assets"), route("b", "get", "/assets/")]).len(), 1);
+        assert_eq!(find_conflicts(&[service("a", "/assets"), route("b", "get", "/assets/{id}/{*rest}")]).len(), 1);
+        assert_eq!(find_conflicts(&[service("a", "/assets"), route("b", "get", "/assets/{name}.png")]).len(), 1);
+    }
+
+    #[test]
+    fn routes_under_a_service_dont_conflict() {
+        assert!(find_conflicts(&[service("a", "/assets"), route("b", "get", "/assets/logo")]).is_empty());
+        assert!(find_conflicts(&[service("a", "/assets"), route("b", "get", "/assets/logo/{*rest}")]).is_empty());
+        assert!(find_conflicts(&[service("a", "/assets"), route("b", "get", "/assets/v{id}")]).is_empty());
+        assert!(find_conflicts(&[service("a", "/assets"), route("b", "get", "/assets/{{raw}}")]).is_empty());
+    }
+
+    #[test]
+    fn routes_beside_a_service_dont_conflict() {
+        assert!(find_conflicts(&[service("a", "/assets"), route("b", "get", "/assets2/{id}"), route("b", "get", "/")]).is_empty());
+    }
+
+    #[test]
+    fn services_of_one_path_conflict() {
+        assert_eq!(find_conflicts(&[service("a", "/assets"), service("b", "/assets")]).len(), 1);
+    }
+
+    #[test]
+    fn fallbacks_conflict() {
+        let fallback = |mod_name| route(mod_name, "any", "").with_kind(RouteKind::Fallback);
+        assert_eq!(find_conflicts(&[fallback("a"), fallback("b")]).len(), 1);
+    }
+
+    #[test]
+    fn route_names_conflict() {
+        let conflicts = find_conflicts(&[
+            route("a", "get", "/a").with_name("detail".to_string()),
+            route("b", "get", "/b").with_name("detail".to_string()),
+        ]);
+        assert!(matches!(conflicts.as_slice(), [RouteConflict::Name { name, .. }] if name == "detail"));
+    }
+}
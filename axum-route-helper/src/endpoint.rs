@@ -0,0 +1,32 @@
+use axum::routing::MethodRouter;
+use axum::{Json, Router};
+use crate::RouteRegistry;
+
+/// the default path of the routes endpoint
+pub const ROUTES_ENDPOINT_PATH: &str = "/__routes";
+
+/// Mount a `GET` endpoint listing every registered route as JSON, for dashboards and ops tools.
+/// It's opt-in and unprotected, see `add_routes_endpoint_with` to put it behind a layer.
+///
+/// ```rust,ignore
+/// let router = add_routes_endpoint(add_routes(Router::new()), ROUTES_ENDPOINT_PATH);
+/// ```
+pub fn add_routes_endpoint<S: Clone + Send + Sync + 'static>(router: Router<S>, path: &str) -> Router<S> {
+    add_routes_endpoint_with(router, path, |endpoint| endpoint)
+}
+
+/// Mount the routes endpoint like `add_routes_endpoint`, `wrap` adding layers to it:
+///
+/// ```rust,ignore
+/// let router = add_routes_endpoint_with(router, "/__admin/routes", |endpoint| {
+///     endpoint.layer(axum::middleware::from_fn(crate::mw::require_admin))
+/// });
+/// ```
+pub fn add_routes_endpoint_with<S, F>(router: Router<S>, path: &str, wrap: F) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+    F: FnOnce(MethodRouter<S>) -> MethodRouter<S>,
+{
+    let endpoint = axum::routing::get(|| async { Json(RouteRegistry::global().routes()) });
+    router.route(path, wrap(endpoint))
+}
@@ -1,53 +1,281 @@
-use std::collections::{HashMap, HashSet};
+use std::any::{Any, TypeId};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs::File;
 use std::{fs, io};
 use std::path::Path;
 use axum::Router;
-use axum::routing::{get};
+use serde::Serialize;
 use std::io::Write;
 
+mod conflict;
+mod endpoint;
+mod registry;
+mod table;
+mod url;
+
+pub use conflict::{find_conflicts, RouteConflict, RouteConflicts};
+pub use endpoint::{add_routes_endpoint, add_routes_endpoint_with, ROUTES_ENDPOINT_PATH};
+pub use registry::{RouteMatch, RouteRegistry};
+pub use table::{format_route_table, format_routes_table, print_route_table, RouteTableFormat};
+pub use url::{fill_path, url_for, UrlForError};
+
 pub trait RouteProvider: Send + Sync + 'static{
-    fn add_route(&self,route: Router) -> Router;
+    /// the state type of the routers this route can be added to, `()` for a plain `Router`
+    fn state_type(&self) -> TypeId;
+    /// add the route to a `Router` of the provider's state type
+    fn add_route(&self,route: AnyRouter) -> AnyRouter;
     fn get_route(&self) -> RouteMethodDesc;
 }
 
+/// A `Router<S>` with its state type erased, so the route providers of all state types share one inventory
+pub struct AnyRouter(Box<dyn Any + Send>);
 
-#[derive(Debug,Clone)]
+impl AnyRouter {
+    pub fn new<S: Clone + Send + Sync + 'static>(router: Router<S>) -> Self {
+        AnyRouter(Box::new(router))
+    }
+
+    /// the wrapped router, panics if it isn't a `Router<S>`
+    pub fn into_router<S: Clone + Send + Sync + 'static>(self) -> Router<S> {
+        match self.0.downcast::<Router<S>>() {
+            Ok(router) => *router,
+            Err(_) => panic!("the router doesn't have the state type {}", std::any::type_name::<S>()),
+        }
+    }
+
+    /// apply `f` to the wrapped `Router<S>`, panics if it isn't one
+    pub fn map<S: Clone + Send + Sync + 'static>(self, f: impl FnOnce(Router<S>) -> Router<S>) -> Self {
+        AnyRouter::new(f(self.into_router()))
+    }
+}
+
+/// What a `RouteMethodDesc` registers
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Default,Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RouteKind {
+    /// a handler fn of `#[route]`, served under its path and methods
+    #[default]
+    Handler,
+    /// the handler fn of `#[fallback]`, serving the requests no route matches
+    Fallback,
+    /// the tower service returned by the fn of `#[nest_service]`, serving everything under its path
+    Service,
+    /// a handler fn taking `WebSocketUpgrade`, or of `#[route(..., kind = "ws")]`
+    WebSocket,
+    /// a handler fn returning `Sse<...>`, or of `#[route(..., kind = "sse")]`
+    Sse,
+}
+
+#[derive(Debug,Clone,Serialize)]
 pub struct RouteMethodDesc {
     pub mod_name:String,
+    /// `module_path!()` of the handler, telling apart same-named modules and handlers
+    pub module_path: String,
+    /// the type of a handler that is an associated fn, see `#[handlers] impl`
+    pub self_type: Option<String>,
+    /// `file!()` of the handler fn, relative to the workspace root
+    pub source_file: String,
+    /// `line!()` of the handler fn's name
+    pub source_line: u32,
     pub path: String,
     pub http_method: String,
+    /// all methods the handler is registered under, `http_method` is the first of them
+    pub http_methods: Vec<String>,
     pub fn_name: String,
     pub fn_args:String,
     pub fn_return_type:String,
     pub use_statements:String,
+    /// stable name from `#[route(..., name = "product.detail")]`, see `url_for`
+    pub name: Option<String>,
+    /// tags of the route and of its `#[handlers]` module, see `add_routes_tagged`
+    pub tags: Vec<String>,
+    /// from `#[route(..., version = "v1")]` or `#[handlers(version = "v1")]`, already part of `path`
+    pub version: Option<String>,
+    /// the note of `#[route(..., deprecated = "use /v2/product")]`, empty for a bare `deprecated`
+    pub deprecated: Option<String>,
+    /// the date of `#[route(..., sunset = "2027-01-01")]`
+    pub sunset: Option<String>,
+    /// a route, a fallback or a nested service, only routes get api clients
+    pub kind: RouteKind,
+    /// the message types a WebSocket client sends and receives, see `RouteKind::WebSocket`
+    pub ws_messages: Option<(String, String)>,
+    /// the payload type of the events of an SSE route, see `RouteKind::Sse`
+    pub sse_event: Option<String>,
+    /// from `#[route(..., priority = 10)]`, routes of higher priority come first, see `add_routes`
+    pub priority: i32,
 }
 
 impl RouteMethodDesc {
     pub fn new(mod_name:String, path: String,http_method: String,fn_name: String,fn_args:String,fn_return_type:String,use_statements:String)->Self{
         RouteMethodDesc {
             mod_name,
+            module_path: String::new(),
+            self_type: None,
+            source_file: String::new(),
+            source_line: 0,
             path,
+            http_methods: vec![http_method.clone()],
             http_method,
             fn_name,
             fn_args,
             fn_return_type,
-            use_statements
+            use_statements,
+            name: None,
+            tags: vec![],
+            version: None,
+            deprecated: None,
+            sunset: None,
+            kind: RouteKind::Handler,
+            ws_messages: None,
+            sse_event: None,
+            priority: 0,
+        }
+    }
+
+    /// set the api version the route is served under
+    pub fn with_version(mut self, version: String) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// mark the route as deprecated, `note` tells what to use instead
+    pub fn with_deprecated(mut self, note: String) -> Self {
+        self.deprecated = Some(note);
+        self
+    }
+
+    /// set the date the route goes away
+    pub fn with_sunset(mut self, sunset: String) -> Self {
+        self.sunset = Some(sunset);
+        self
+    }
+
+    /// set what the description registers
+    pub fn with_kind(mut self, kind: RouteKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// set the message types the client of a WebSocket route sends and receives
+    pub fn with_ws_messages(mut self, send: String, receive: String) -> Self {
+        self.ws_messages = Some((send, receive));
+        self
+    }
+
+    /// set the payload type of the events of an SSE route
+    pub fn with_sse_event(mut self, sse_event: String) -> Self {
+        self.sse_event = Some(sse_event);
+        self
+    }
+
+    /// set the priority the route is ordered by, see `add_routes`
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// the order of registration and code generation: higher priority first,
+    /// then by path, method, module and fn name
+    pub fn cmp_order(&self, other: &RouteMethodDesc) -> Ordering {
+        other.priority.cmp(&self.priority)
+            .then_with(|| self.path.cmp(&other.path))
+            .then_with(|| self.http_method.cmp(&other.http_method))
+            .then_with(|| self.mod_name.cmp(&other.mod_name))
+            .then_with(|| self.module_path.cmp(&other.module_path))
+            .then_with(|| self.fn_name.cmp(&other.fn_name))
+    }
+
+    /// set the `module_path!()` of the handler
+    pub fn with_module_path(mut self, module_path: String) -> Self {
+        self.module_path = module_path;
+        self
+    }
+
+    /// set where the handler fn is declared
+    pub fn with_source_location(mut self, source_file: String, source_line: u32) -> Self {
+        self.source_file = source_file;
+        self.source_line = source_line;
+        self
+    }
+
+    /// where the handler fn is declared, e.g. `src/shop/product.rs:12`, empty if unknown
+    pub fn source_location(&self) -> String {
+        if self.source_file.is_empty() {
+            String::new()
+        } else {
+            format!("{}:{}", self.source_file, self.source_line)
+        }
+    }
+
+    /// set the type of a handler that is an associated fn
+    pub fn with_self_type(mut self, self_type: String) -> Self {
+        self.self_type = Some(self_type);
+        self
+    }
+
+    /// the path of the handler fn, e.g. `shop::product::get_product` or `shop::ProductController::get`,
+    /// `product::get_product` without module path
+    pub fn handler_path(&self) -> String {
+        let module = if self.module_path.is_empty() { &self.mod_name } else { &self.module_path };
+        match &self.self_type {
+            Some(self_type) => format!("{}::{}::{}", module, self_type, self.fn_name),
+            None => format!("{}::{}", module, self.fn_name),
         }
     }
+
+    /// set the tags the route is selected by in `add_routes_tagged`
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// whether the route is tagged with `tag`
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|it| it == tag)
+    }
+
+    /// set the name the route is found by in `url_for`
+    pub fn with_name(mut self, name: String) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// set the methods of a handler registered under several of them
+    pub fn with_http_methods(mut self, http_methods: Vec<String>) -> Self {
+        if let Some(first) = http_methods.first() {
+            self.http_method = first.clone();
+        }
+        self.http_methods = http_methods;
+        self
+    }
+
+    /// one description per method, the first keeps the fn name while the others get the method as suffix, e.g. get_product_head,
+    /// whose client returns `()` as HEAD responses have no body
+    pub fn split_by_method(&self) -> Vec<RouteMethodDesc> {
+        self.http_methods.iter().enumerate().map(|(index, method)| {
+            let mut desc = self.clone();
+            desc.http_method = method.clone();
+            desc.http_methods = vec![method.clone()];
+            if index > 0 {
+                desc.fn_name = format!("{}_{}", self.fn_name, method.to_lowercase());
+            }
+            desc
+        }).collect()
+    }
 }
 
 ///how the param value is being provided
 #[derive(Debug,Clone,PartialEq)]
 pub enum FnArgValueForm{
-    Json,Path,QueryString
+    Json,Form,Path,QueryString
 }
 
 #[derive(Debug,Clone)]
 pub struct FnArgInfo {
     pub name:String,
     pub arg_type:String,//String,i32,Product,User, etc.
-    pub value_form:FnArgValueForm,//Json,Path,QueryString, where QueryString is the default
+    pub value_form:FnArgValueForm,//Json,Form,Path,QueryString, where QueryString is the default
 }
 
 ///Api clients code generation configuration
@@ -55,7 +283,13 @@ pub struct ApiClientCodeGenConf {
     pub output_dir:String,
     pub http_client_path:String,
     pub api_error_path:String,
-    pub response_wrapper_path:String
+    pub response_wrapper_path:String,
+    /// generate only the routes of this version, see `with_version`
+    pub version:Option<String>,
+    /// the connection type returned by the clients of WebSocket routes, see `with_websocket_connection_path`
+    pub websocket_connection_path:String,
+    /// the stream type returned by the clients of SSE routes, see `with_event_stream_path`
+    pub event_stream_path:String,
 }
 
 impl ApiClientCodeGenConf {
@@ -64,9 +298,33 @@ impl ApiClientCodeGenConf {
              output_dir,
              http_client_path,
              api_error_path,
-             response_wrapper_path
+             response_wrapper_path,
+             version: None,
+             websocket_connection_path: "crate::WebSocketConnection".to_string(),
+             event_stream_path: "crate::EventStream".to_string(),
         }
     }
+
+    /// set the connection type of the WebSocket clients, `crate::WebSocketConnection` by default.
+    /// The clients call `HttpClient::websocket(path, &query)`, returning a `WebSocketConnection<Send, Receive>`
+    pub fn with_websocket_connection_path(mut self, websocket_connection_path: &str) -> Self {
+        self.websocket_connection_path = websocket_connection_path.to_string();
+        self
+    }
+
+    /// set the stream type of the SSE clients, `crate::EventStream` by default.
+    /// The clients call `HttpClient::sse(path, &query)`, returning an `EventStream<Event>`,
+    /// a `Stream` of the decoded events
+    pub fn with_event_stream_path(mut self, event_stream_path: &str) -> Self {
+        self.event_stream_path = event_stream_path.to_string();
+        self
+    }
+
+    /// generate only the clients of `version`, with the routes without version, flat into `output_dir`
+    pub fn with_version(mut self, version: &str) -> Self {
+        self.version = Some(version.to_string());
+        self
+    }
 }
 
 #[macro_export]
@@ -80,41 +338,167 @@ macro_rules! register_route_provider {
 
 inventory::collect!(&'static dyn RouteProvider);
 
-///add routes
-pub fn add_routes(router: Router) -> Router {
-    let mut router = router;
-    for route_provider in inventory::iter::<&dyn RouteProvider>{
+///add the routes declared for the router's state type `S`, see `#[handlers(state = AppState)]`.
+///Routes of other state types are skipped, e.g. stateless routes are added by a separate
+///`add_routes(Router::new())` and merged after `with_state`.
+///Panics listing every conflicting route, see `try_add_routes`.
+///
+///The routes are added in a stable order, whatever order the linker collected them in:
+///higher `priority` first, then by path, method, module and fn name, see `RouteMethodDesc::cmp_order`.
+pub fn add_routes<S: Clone + Send + Sync + 'static>(router: Router<S>) -> Router<S> {
+    try_add_routes(router).unwrap_or_else(|conflicts| panic!("{}", conflicts))
+}
+
+///add only the routes `filter` accepts, e.g. `add_routes_with(router, |desc| desc.mod_name == "product")`
+pub fn add_routes_with<S, F>(router: Router<S>, filter: F) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+    F: Fn(&RouteMethodDesc) -> bool,
+{
+    try_add_routes_with(router, filter).unwrap_or_else(|conflicts| panic!("{}", conflicts))
+}
+
+///add only the routes tagged with `tag`, so each service of a binary mounts its own slice of the routes
+pub fn add_routes_tagged<S: Clone + Send + Sync + 'static>(router: Router<S>, tag: &str) -> Router<S> {
+    add_routes_with(router, |desc| desc.has_tag(tag))
+}
+
+///add the routes like `add_routes`, failing with every conflicting route instead of letting axum panic on the first
+pub fn try_add_routes<S: Clone + Send + Sync + 'static>(router: Router<S>) -> Result<Router<S>, RouteConflicts> {
+    try_add_routes_with(router, |_| true)
+}
+
+///add the routes `filter` accepts like `add_routes_with`, failing with every conflicting route among them
+pub fn try_add_routes_with<S, F>(router: Router<S>, filter: F) -> Result<Router<S>, RouteConflicts>
+where
+    S: Clone + Send + Sync + 'static,
+    F: Fn(&RouteMethodDesc) -> bool,
+{
+    let (route_providers, descs): (Vec<&dyn RouteProvider>, Vec<RouteMethodDesc>) = sorted_route_providers()
+        .into_iter()
+        .filter(|(route_provider, desc)| route_provider.state_type() == TypeId::of::<S>() && filter(desc))
+        .unzip();
+
+    let conflicts = find_conflicts(&descs);
+    if !conflicts.is_empty() {
+        return Err(RouteConflicts(conflicts));
+    }
+
+    let mut router = AnyRouter::new(router);
+    for route_provider in route_providers {
         router = route_provider.add_route(router);
     }
-    router
+    Ok(router.into_router())
 }
 
-///get routes description
-pub fn get_routes_desc() -> Vec<RouteMethodDesc> {
-    let mut route_method_descs:Vec<RouteMethodDesc> = vec![];
-    for route_provider in inventory::iter::<&dyn RouteProvider>{
-        route_method_descs.push(route_provider.get_route());
+///check all registered routes, of every state type, for conflicts
+pub fn check_routes() -> Result<(), RouteConflicts> {
+    let conflicts = find_conflicts(&get_routes_desc());
+    if conflicts.is_empty() {
+        Ok(())
+    } else {
+        Err(RouteConflicts(conflicts))
     }
-    route_method_descs
+}
+
+///the route providers with their descriptions, in the order of `RouteMethodDesc::cmp_order`
+fn sorted_route_providers() -> Vec<(&'static dyn RouteProvider, RouteMethodDesc)> {
+    let mut route_providers: Vec<(&'static dyn RouteProvider, RouteMethodDesc)> = inventory::iter::<&dyn RouteProvider>
+        .into_iter()
+        .map(|route_provider| (*route_provider, route_provider.get_route()))
+        .collect();
+    route_providers.sort_by(|(_, a), (_, b)| a.cmp_order(b));
+    route_providers
+}
+
+///get routes description, in the same order the routes are added in
+pub fn get_routes_desc() -> Vec<RouteMethodDesc> {
+    sorted_route_providers().into_iter().map(|(_, desc)| desc).collect()
 }
 
 /// Generate API clients code
+///
+/// The output is the same on every build: one file per module, in module name order,
+/// with its imports sorted and its functions in the order of `get_routes_desc`.
+///
+/// Versioned routes get a module tree of their own, e.g. `v1/product_api_client.rs` declared by `pub mod v1;`,
+/// next to the clients of the routes without version. With `ApiClientCodeGenConf::with_version`
+/// only that version is generated, together with the routes without version.
 pub fn generate_api_client(conf:ApiClientCodeGenConf) -> io::Result<()>{
+    let dir_path = Path::new(&conf.output_dir);
+    // fallbacks and nested services have no api to call
+    let routes: Vec<RouteMethodDesc> = get_routes_desc().into_iter()
+        .filter(|route| matches!(route.kind, RouteKind::Handler | RouteKind::WebSocket | RouteKind::Sse))
+        .collect();
 
-    let output_dir = conf.output_dir;
-    let http_client_path = conf.http_client_path;
-    let api_error_path = conf.api_error_path;
-    let response_wrapper_path = conf.response_wrapper_path;
+    if let Some(version) = conf.version.as_deref() {
+        let routes = routes.into_iter()
+            .filter(|route| route.version.as_deref().is_none_or(|it| it == version))
+            .collect();
+        return generate_api_client_modules(&conf, dir_path, routes, vec![]);
+    }
 
-    let dir_path = Path::new(&output_dir);
-    prepare_directory(dir_path.clone());
-    let routes = get_routes_desc();
-    // Group routes by mod_name
-    let mut grouped_routes: HashMap<String, Vec<RouteMethodDesc>> = HashMap::new();
+    // Group routes by version, the routes without version stay at the top
+    let mut versioned_routes: BTreeMap<String, Vec<RouteMethodDesc>> = BTreeMap::new();
+    let mut routes_without_version: Vec<RouteMethodDesc> = vec![];
     for route in routes {
-        grouped_routes.entry(route.mod_name.clone()).or_default().push(route);
+        match route.version.clone() {
+            Some(version) => versioned_routes.entry(version).or_default().push(route),
+            None => routes_without_version.push(route),
+        }
     }
-    let skip_statements = vec!["axum::Json","axum::extract::Path","axum::extract::Query","axum_extra::extract::Query"];
+    let mut version_mods: Vec<String> = vec![];
+    for (version, routes) in versioned_routes {
+        let version_mod = version_mod_name(&version);
+        generate_api_client_modules(&conf, &dir_path.join(&version_mod), routes, vec![])?;
+        version_mods.push(version_mod);
+    }
+    generate_api_client_modules(&conf, dir_path, routes_without_version, version_mods)
+}
+
+/// the module of a version's clients, e.g. `v1` for "v1" and `v2024_01` for "2024-01"
+fn version_mod_name(version: &str) -> String {
+    let name = version.replace(|c: char| !c.is_ascii_alphanumeric(), "_");
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("v{}", name)
+    } else {
+        name
+    }
+}
+
+/// the client module of a route whose mod_name is shared by several modules, from its module path
+/// without the crate, e.g. `admin_product` for `shop::admin::product`
+fn client_mod_name(route: &RouteMethodDesc) -> String {
+    let mut segments: Vec<&str> = route.module_path.split("::").skip(1).collect();
+    if segments.last() != Some(&route.mod_name.as_str()) {
+        segments.push(&route.mod_name);
+    }
+    segments.join("_")
+}
+
+/// write a client file per module of `routes` and the `mod.rs` declaring them and the `sub_mods`
+fn generate_api_client_modules(conf: &ApiClientCodeGenConf, dir_path: &Path, routes: Vec<RouteMethodDesc>, sub_mods: Vec<String>) -> io::Result<()> {
+    let http_client_path = &conf.http_client_path;
+    let api_error_path = &conf.api_error_path;
+    let response_wrapper_path = &conf.response_wrapper_path;
+
+    prepare_directory(dir_path);
+    // Group routes by mod_name, same-named modules of different paths get a client each
+    let mut module_paths: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for route in routes.iter() {
+        module_paths.entry(route.mod_name.as_str()).or_default().insert(route.module_path.as_str());
+    }
+    let client_mod_names: Vec<String> = routes.iter()
+        .map(|route| match module_paths[route.mod_name.as_str()].len() {
+            1 => route.mod_name.clone(),
+            _ => client_mod_name(route),
+        })
+        .collect();
+    let mut grouped_routes: BTreeMap<String, Vec<RouteMethodDesc>> = BTreeMap::new();
+    for (route, mod_name) in routes.into_iter().zip(client_mod_names) {
+        grouped_routes.entry(mod_name).or_default().push(route);
+    }
+    let skip_statements = ["axum::Json","axum::extract::Path","axum::extract::Query","axum_extra::extract::Query","axum::Form","axum::extract::Form","axum::extract::State","axum::extract::WebSocketUpgrade","axum::extract::ws::WebSocketUpgrade"];
     let mut mod_names:Vec<String> = vec![];
 
     // Iterate over each group and generate the corresponding file
@@ -131,7 +515,7 @@ pub fn generate_api_client(conf:ApiClientCodeGenConf) -> io::Result<()>{
             .collect::<Vec<String>>() // Collect into a Vec<&str>
             .join(";"); // Join the Vec with "; " as separator
 
-        let statements: HashSet<String> = use_statements.split(';')
+        let statements: BTreeSet<String> = use_statements.split(';')
             .map(|s| s.trim())
             .filter(|s| !s.is_empty() && !skip_statements.contains(s))
             .map(|s| format!("use {};", s))
@@ -140,24 +524,33 @@ pub fn generate_api_client(conf:ApiClientCodeGenConf) -> io::Result<()>{
         writeln!(file, "use {};", http_client_path)?;
         writeln!(file, "use {};", api_error_path)?;
         writeln!(file, "use {};", response_wrapper_path)?;
+        if method_descs.iter().any(|desc| desc.kind == RouteKind::WebSocket) {
+            writeln!(file, "use {};", conf.websocket_connection_path)?;
+        }
+        if method_descs.iter().any(|desc| desc.kind == RouteKind::Sse) {
+            writeln!(file, "use {};", conf.event_stream_path)?;
+        }
         for statement in statements {
             writeln!(file, "{}", statement)?;
         }
 
         writeln!(file, "\n")?;
 
-        // Generate API client functions
-        for desc in method_descs {
-            let fn_code = generate_fn_code(&desc);
+        // Generate API client functions, one per method
+        for desc in method_descs.iter().flat_map(|desc| desc.split_by_method()) {
+            let fn_code = generate_fn_code(&desc)?;
             writeln!(file, "{}\n", fn_code)?;
         }
     }
 
     //create a mod.rs
-    let file_path = dir_path.join("mod.rs".to_string());
+    let file_path = dir_path.join("mod.rs");
     let mut file = File::create(&file_path)?;
 
     // Write each mod declaration
+    for sub_mod in sub_mods {
+        writeln!(file, "pub mod {};", sub_mod)?;
+    }
     for mod_name in mod_names {
         writeln!(file, "pub mod {}_api_client;",mod_name)?;
     }
@@ -167,7 +560,7 @@ pub fn generate_api_client(conf:ApiClientCodeGenConf) -> io::Result<()>{
 
 pub fn prepare_directory(path:& std::path::Path){
     if !path.exists() {
-        fs::create_dir_all(path).expect(&format!("Failed to create directory {}", path.display()));
+        fs::create_dir_all(path).unwrap_or_else(|_| panic!("Failed to create directory {}", path.display()));
     }
 }
 
@@ -192,16 +585,20 @@ pub fn prepare_directory(path:& std::path::Path){
 ///     use_statements: "axum::Json;shared::request::response::ApiResponse;shared::entity::Product;axum::extract::Path",
 /// }
 ///
-fn generate_fn_code(desc: &RouteMethodDesc) -> String {
+fn generate_fn_code(desc: &RouteMethodDesc) -> io::Result<String> {
     println!("desc {:#?}", desc);
 
+    if matches!(desc.kind, RouteKind::WebSocket | RouteKind::Sse) {
+        return generate_stream_fn_code(desc);
+    }
+
     let fn_name = &desc.fn_name;
     let path = &desc.path;
     let http_method = desc.http_method.to_lowercase(); // Ensure the method is in lowercase
     let fn_return_type = &desc.fn_return_type;
 
     // Process the function arguments
-    let fn_args_info = parts_fn_args_names_and_types(desc.fn_args.clone());
+    let fn_args_info = client_fn_args_info(&desc.fn_args)?;
 
     println!("fn_args_info = {:#?}",fn_args_info);
 
@@ -246,6 +643,13 @@ fn generate_fn_code(desc: &RouteMethodDesc) -> String {
         fn_return_data_type = format!("{}{}", fn_return_data_type, closing_brackets);
     }
 
+    // a HEAD response has no body to decode
+    let (fn_return_type, fn_return_data_type) = if http_method == "head" {
+        ("()".to_string(), "()".to_string())
+    } else {
+        (fn_return_type, fn_return_data_type)
+    };
+
     let path = if path.contains("{") { //e.g., /api/product/{id}/{action}
         //should be format!("/api/product/{}/{}",id,action)
         path.to_string()
@@ -253,15 +657,102 @@ fn generate_fn_code(desc: &RouteMethodDesc) -> String {
         path.to_string()
     };
 
-    format!(
-        r#"pub async fn {fn_name}({fn_args}) -> Result<{fn_return_data_type}, ApiError> {{
+    let deprecated = deprecated_attr(desc);
+
+    Ok(format!(
+        r#"{deprecated}pub async fn {fn_name}({fn_args}) -> Result<{fn_return_data_type}, ApiError> {{
         let result = HttpClient::{}.await?;
         Ok(result)
     }}"#,
         // Generate the vector of arguments for the HttpClient call
         //generate_http_client_method(&http_method, fn_args_info.iter().any(|it|it.value_form == FnArgValueForm::Json)),
         generate_http_client_call(http_method,path, fn_args_info, fn_return_type.clone())
-    )
+    ))
+}
+
+/// the `#[deprecated]` attribute warning the callers of a deprecated route's client function
+fn deprecated_attr(desc: &RouteMethodDesc) -> String {
+    match (&desc.deprecated, &desc.sunset) {
+        (None, _) => "".to_string(),
+        (Some(note), None) if note.is_empty() => "#[deprecated]\n".to_string(),
+        (Some(note), None) => format!("#[deprecated(note = {:?})]\n", note),
+        (Some(note), Some(sunset)) if note.is_empty() => format!("#[deprecated(note = {:?})]\n", format!("sunset on {}", sunset)),
+        (Some(note), Some(sunset)) => format!("#[deprecated(note = {:?})]\n", format!("{}, sunset on {}", note, sunset)),
+    }
+}
+
+/// Generate the client function of a WebSocket route, opening a connection typed by the route's messages,
+/// or of an SSE route, opening a stream of its decoded events
+///
+/// desc RouteMethodDesc {
+///     path: "/ws/chat/{room}",
+///     fn_name: "chat",
+///     fn_args: "ws:WebSocketUpgrade;Path(room):Path<String>",
+///     ws_messages: Some(("ClientMessage", "ServerMessage")),
+/// }
+/// pub async fn chat(room: String) -> Result<WebSocketConnection<ClientMessage, ServerMessage>, ApiError> {
+///     let result = HttpClient::websocket(&format!("/ws/chat/{}", room), &Option::<i8>::None).await?;
+///     Ok(result)
+/// }
+/// desc RouteMethodDesc {
+///     path: "/api/ticks",
+///     fn_name: "ticks",
+///     fn_args: "Query(filter):Query<TickFilter>",
+///     sse_event: Some("Tick"),
+/// }
+/// pub async fn ticks(filter: TickFilter) -> Result<EventStream<Tick>, ApiError> {
+///     let result = HttpClient::sse("/api/ticks", &Some(filter)).await?;
+///     Ok(result)
+/// }
+fn generate_stream_fn_code(desc: &RouteMethodDesc) -> io::Result<String> {
+    let fn_args_info = client_fn_args_info(&desc.fn_args)?;
+
+    let mut path = desc.path.clone();
+    let mut path_params: Vec<String> = vec![];
+    let mut query: Option<String> = None;
+    for it in fn_args_info.iter() {
+        match it.value_form {
+            FnArgValueForm::Path => {
+                path = path.replace(&format!("{{{}}}", it.name), "{}");
+                path_params.push(it.name.clone());
+            },
+            FnArgValueForm::QueryString | FnArgValueForm::Json | FnArgValueForm::Form => {
+                if query.is_none() {
+                    query = Some(it.name.clone());
+                }
+            },
+        }
+    }
+    let path = if path_params.is_empty() {
+        format!("\"{}\"", path)
+    } else {
+        format!("&format!(\"{}\", {})", path, path_params.join(", "))
+    };
+    let query = match query {
+        Some(query) => format!("&Some({})", query),
+        None => "&Option::<i8>::None".to_string(),
+    };
+    let fn_args: String = fn_args_info.iter()
+        .map(|item| format!("{}: {}", item.name, item.arg_type))
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    let (return_type, client_fn) = if desc.kind == RouteKind::Sse {
+        let event = desc.sse_event.clone().unwrap_or_else(|| "String".to_string());
+        (format!("EventStream<{}>", event), "sse")
+    } else {
+        let (send, receive) = desc.ws_messages.clone().unwrap_or_else(|| ("String".to_string(), "String".to_string()));
+        (format!("WebSocketConnection<{}, {}>", send, receive), "websocket")
+    };
+
+    Ok(format!(
+        r#"{deprecated}pub async fn {fn_name}({fn_args}) -> Result<{return_type}, ApiError> {{
+        let result = HttpClient::{client_fn}({path}, {query}).await?;
+        Ok(result)
+    }}"#,
+        deprecated = deprecated_attr(desc),
+        fn_name = desc.fn_name,
+    ))
 }
 
 /*fn generate_http_client_method(http_method: &str,has_json:bool) -> String {
@@ -274,10 +765,14 @@ fn generate_fn_code(desc: &RouteMethodDesc) -> String {
 
 fn generate_http_client_call(http_method:String, mut path:String, fn_args_info:Vec<FnArgInfo>, fn_return_type:String) -> String {
     let mut path_params:Vec<String> = vec![];
-    let mut query_string_params:Vec<String> = vec![];
+    let query_string_params:Vec<String> = vec![];
     let mut body:Option<FnArgInfo> = None;
     //method
     let mut method = http_method.to_lowercase();
+    // a form body is url-encoded rather than sent as JSON, e.g. `post_form`
+    if fn_args_info.iter().any(|it| it.value_form == FnArgValueForm::Form) {
+        method.push_str("_form");
+    }
     let is_api_response = fn_return_type.contains("ApiResponse");
     let is_paging_response = fn_return_type.contains("PagingResponse");
     if is_paging_response {
@@ -287,7 +782,7 @@ fn generate_http_client_call(http_method:String, mut path:String, fn_args_info:V
     //path
     fn_args_info.iter().for_each(|it|{
         match it.value_form {
-            FnArgValueForm::Json => {
+            FnArgValueForm::Json | FnArgValueForm::Form => {
                 if body.is_none() {
                     body = Some(it.clone());
                 }
@@ -302,9 +797,6 @@ fn generate_http_client_call(http_method:String, mut path:String, fn_args_info:V
                     body = Some(it.clone());
                 }
             },
-            _ => {
-                query_string_params.push(it.name.clone());
-            }
         }
     });
 
@@ -319,21 +811,21 @@ fn generate_http_client_call(http_method:String, mut path:String, fn_args_info:V
 
     //post body
     //if method == "post" {
-        if body.is_some() {
+        if let Some(body) = body {
             http_client_call.push_str(", &");
-            http_client_call.push_str(&format!("Some({})",body.unwrap().name.as_str()));
+            http_client_call.push_str(&format!("Some({})",body.name.as_str()));
         }else {
             http_client_call.push_str(", &Option::<i8>::None");
         }
     //}
 
     //params
-    if query_string_params.len() > 0 {
+    if !query_string_params.is_empty() {
         http_client_call.push_str(", vec![");
         query_string_params.iter().for_each(|it|{
             http_client_call.push_str(&format!("(\"{}\",{})",&it,&it));
         });
-        http_client_call.push_str("]");
+        http_client_call.push(']');
     }else {
         http_client_call.push_str(", vec![]");
     }
@@ -346,48 +838,64 @@ fn generate_http_client_call(http_method:String, mut path:String, fn_args_info:V
             http_client_call.push_str(", ResponseWrapper::Nothing");
         }
     }
-    http_client_call.push_str(")");
+    http_client_call.push(')');
 
     http_client_call
 }
 
-/// Generate the argument list for the HttpClient call
-fn generate_http_client_args(fn_args: &str) -> String {
-    fn_args
-        .split(',')
-        .map(|arg| {
-            let trimmed = arg.trim();
-            if trimmed.is_empty() {
-                return String::new();
-            }
-            let parts: Vec<&str> = trimmed.split(':').collect();
-            if parts.len() != 2 {
-                return String::new(); // Handle unexpected formats
-            }
-            let arg_name = parts[0].trim();
-            format!(r#"("{}",{})"#, arg_name, arg_name) // Expecting arg names to match the values
-        })
-        .filter(|s| !s.is_empty()) // Remove empty strings
-        .collect::<Vec<String>>()
-        .join(",")
+/// the extractors whose values a client sends, the others, e.g. `State`, `HeaderMap` or `WebSocketUpgrade`, are the server's own
+const CLIENT_EXTRACTORS: [&str; 4] = ["Path", "Query", "Json", "Form"];
+
+/// the arguments a client passes, each `;` separated handler argument parsed on its own,
+/// e.g. `State(state):State<AppState>;Json(product):Json<Product>` gives `product: Product`
+fn client_fn_args_info(fn_args: &str) -> io::Result<Vec<FnArgInfo>> {
+    let mut fn_args_info = vec![];
+    for arg in fn_args.split(';').filter(|arg| CLIENT_EXTRACTORS.iter().any(|extractor| is_extractor(arg, extractor))) {
+        fn_args_info.extend(parts_fn_args_names_and_types(arg.to_string())?);
+    }
+    Ok(fn_args_info)
+}
+
+/// whether the handler argument `pattern:Type<..>` is of the extractor named `extractor`, path and generics aside
+fn is_extractor(arg: &str, extractor: &str) -> bool {
+    split_fn_arg(arg).is_some_and(|(_, arg_type)| arg_type.split('<').next() == Some(extractor))
+}
+
+/// split a handler argument at the `:` between its pattern and type, not at a `::` path separator,
+/// unqualifying the extractor, e.g. `axum::extract::Path(id):axum::extract::Path<u32>` gives `Path(id)` and `Path<u32>`
+fn split_fn_arg(arg: &str) -> Option<(&str, &str)> {
+    let bytes = arg.as_bytes();
+    let index = (0..bytes.len())
+        .find(|&index| bytes[index] == b':' && bytes.get(index + 1) != Some(&b':') && (index == 0 || bytes[index - 1] != b':'))?;
+    Some((unqualify(&arg[..index]), unqualify(&arg[index + 1..])))
 }
 
+/// strip the path of the outermost name, `axum::Json<shared::Product>` gives `Json<shared::Product>`
+fn unqualify(name: &str) -> &str {
+    let outer_end = name.find(['<', '(']).unwrap_or(name.len());
+    match name[..outer_end].rfind("::") {
+        Some(index) => &name[index + 2..],
+        None => name,
+    }
+}
 
 ///解析方法参数名、类型
-fn parts_fn_args_names_and_types(fn_args: String) -> Vec<FnArgInfo> {
+fn parts_fn_args_names_and_types(fn_args: String) -> io::Result<Vec<FnArgInfo>> {
     let mut fn_args_info: Vec<FnArgInfo> = vec![];
-    let fn_args_names_and_types: Vec<&str> = fn_args.split(':').collect();
 
-    if fn_args_names_and_types.len() == 2 { // Only two are acceptable
-        let names_str = fn_args_names_and_types[0].trim(); // e.g., "Json(product)" or "Path((id,action))"
-        let types_str = fn_args_names_and_types[1].trim(); // e.g., "Json<Product>" or "Path<(String,String)>"
+    if let Some((names_str, types_str)) = split_fn_arg(&fn_args) {
+        let names_str = names_str.trim(); // e.g., "Json(product)" or "Path((id,action))"
+        let types_str = types_str.trim(); // e.g., "Json<Product>" or "Path<(String,String)>"
 
-        let mut value_form = FnArgValueForm::QueryString; //default
+        let value_form; //QueryString is the default
 
         // Clean up names_str
         let cleaned_names_str = if names_str.starts_with("Json(") {
             value_form = FnArgValueForm::Json;
             &names_str[5..names_str.len() - 1] // Remove "Json(" prefix and ")" suffix
+        } else if names_str.starts_with("Form(") {
+            value_form = FnArgValueForm::Form;
+            &names_str[5..names_str.len() - 1] // Remove "Form(" prefix and ")" suffix
         } else if names_str.starts_with("Query(") {
             value_form = FnArgValueForm::QueryString;
             &names_str[6..names_str.len() - 1] // Remove "Json(" prefix and ")" suffix
@@ -408,8 +916,8 @@ fn parts_fn_args_names_and_types(fn_args: String) -> Vec<FnArgInfo> {
         let names: Vec<String> = cleaned_names_str.split(",").map(|p| p.trim().to_string()).collect();
 
         // Clean up types_str
-        let cleaned_types_str = if types_str.starts_with("Json<") {
-            &types_str[5..types_str.len() - 1] // Remove "Json<" and ">"
+        let cleaned_types_str = if types_str.starts_with("Json<") || types_str.starts_with("Form<") {
+            &types_str[5..types_str.len() - 1] // Remove "Json<" or "Form<" and ">"
         }else if types_str.starts_with("Query<") {
             &types_str[6..types_str.len() - 1] // Remove "Json<" and ">"
         } else if types_str.starts_with("Path<") {
@@ -428,15 +936,18 @@ fn parts_fn_args_names_and_types(fn_args: String) -> Vec<FnArgInfo> {
 
         // Create FnArgInfo for each name/type pair
         for (index, name) in names.iter().enumerate() {
+            let Some(arg_type) = types.get(index) else {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("No type for `{}` in the handler argument `{}`", name, fn_args)));
+            };
             fn_args_info.push(FnArgInfo {
                 name: name.to_string(),
-                arg_type: types[index].clone(),
+                arg_type: arg_type.clone(),
                 value_form: value_form.clone(),
             });
         }
     }
 
-    fn_args_info
+    Ok(fn_args_info)
 }
 /*fn parts_fn_args_names_and_types(fn_args:String) -> (Vec<String>,Vec<String>) {
     let mut names:Vec<String> = vec![];
@@ -481,3 +992,35 @@ fn parts_fn_args_names_and_types(fn_args: String) -> Vec<FnArgInfo> {
     }
     (names,types)
 }*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names_and_types(fn_args: &str) -> Vec<(String, String, FnArgValueForm)> {
+        client_fn_args_info(fn_args).unwrap().into_iter().map(|it| (it.name, it.arg_type, it.value_form)).collect()
+    }
+
+    #[test]
+    fn parses_the_extracted_args() {
+        assert_eq!(names_and_types("Path((id,action)):Path<(u32,String)>;Query(filter):Query<Filter>"), vec![
+            ("id".to_string(), "u32".to_string(), FnArgValueForm::Path),
+            ("action".to_string(), "String".to_string(), FnArgValueForm::Path),
+            ("filter".to_string(), "Filter".to_string(), FnArgValueForm::QueryString),
+        ]);
+        assert_eq!(names_and_types("axum::Form(login):axum::Form<Login>"), vec![("login".to_string(), "Login".to_string(), FnArgValueForm::Form)]);
+    }
+
+    #[test]
+    fn skips_the_servers_own_extractors() {
+        let fn_args = "State(state):State<AppState>;headers:HeaderMap;Extension(user):Extension<User>;ConnectInfo(addr):ConnectInfo<SocketAddr>;ws:WebSocketUpgrade;Json(product):Json<Product>";
+        assert_eq!(names_and_types(fn_args), vec![("product".to_string(), "Product".to_string(), FnArgValueForm::Json)]);
+    }
+
+    #[test]
+    fn fails_on_a_name_without_a_type() {
+        let error = client_fn_args_info("Path((id,action)):Path<u32>").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("`action`"));
+    }
+}
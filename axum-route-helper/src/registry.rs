@@ -0,0 +1,308 @@
+use std::sync::OnceLock;
+use crate::conflict::normalize_path;
+use crate::{get_routes_desc, RouteKind, RouteMethodDesc};
+
+/// The registered routes, collected once from the inventory, with lookups for admin tools and request logging:
+///
+/// ```rust,ignore
+/// let registry = RouteRegistry::global();
+/// let admin_routes = registry.by_tag("admin");
+/// if let Some(found) = registry.match_path("GET", "/api/product/42/view") {
+///     tracing::info!(handler = %found.route.handler_path(), params = ?found.params);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RouteRegistry {
+    routes: Vec<RouteMethodDesc>,
+}
+
+/// A route matching a concrete request path, with the values of its placeholders
+#[derive(Debug, Clone)]
+pub struct RouteMatch<'a> {
+    pub route: &'a RouteMethodDesc,
+    /// placeholder names and their percent-decoded values, e.g. `[("id", "42")]`
+    pub params: Vec<(String, String)>,
+}
+
+/// how specific a matched path segment is, axum prefers static segments over placeholders over wildcards
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum SegmentRank {
+    Wildcard,
+    Placeholder,
+    Static,
+}
+
+impl RouteRegistry {
+    /// collect the registered routes, in the order of `get_routes_desc`
+    pub fn new() -> Self {
+        RouteRegistry::from_routes(get_routes_desc())
+    }
+
+    /// a registry of the given routes
+    pub fn from_routes(routes: Vec<RouteMethodDesc>) -> Self {
+        RouteRegistry { routes }
+    }
+
+    /// the registry of the registered routes, collected on first use
+    pub fn global() -> &'static RouteRegistry {
+        static REGISTRY: OnceLock<RouteRegistry> = OnceLock::new();
+        REGISTRY.get_or_init(RouteRegistry::new)
+    }
+
+    pub fn routes(&self) -> &[RouteMethodDesc] {
+        &self.routes
+    }
+
+    /// the routes of a module, by its name like `product` or its path like `shop::product`
+    pub fn by_module(&self, module: &str) -> Vec<&RouteMethodDesc> {
+        self.find(|route| route.mod_name == module || route.module_path == module)
+    }
+
+    /// the routes served for an HTTP method, in any case
+    pub fn by_method(&self, method: &str) -> Vec<&RouteMethodDesc> {
+        self.find(|route| route.http_methods.iter().any(|it| it.eq_ignore_ascii_case(method)))
+    }
+
+    /// the routes of a path template like `/api/product/{id}`, placeholder names aside
+    pub fn by_path(&self, template: &str) -> Vec<&RouteMethodDesc> {
+        let template = normalize_path(template);
+        self.find(|route| normalize_path(&route.path) == template)
+    }
+
+    /// the routes of handler fns named `fn_name`
+    pub fn by_fn_name(&self, fn_name: &str) -> Vec<&RouteMethodDesc> {
+        self.find(|route| route.fn_name == fn_name)
+    }
+
+    pub fn by_tag(&self, tag: &str) -> Vec<&RouteMethodDesc> {
+        self.find(|route| route.has_tag(tag))
+    }
+
+    /// the route named by `#[route(..., name = "product.detail")]`
+    pub fn by_name(&self, name: &str) -> Option<&RouteMethodDesc> {
+        self.routes.iter().find(|route| route.name.as_deref() == Some(name))
+    }
+
+    fn find(&self, predicate: impl Fn(&RouteMethodDesc) -> bool) -> Vec<&RouteMethodDesc> {
+        self.routes.iter().filter(|route| predicate(route)).collect()
+    }
+
+    /// The route serving a request, like axum's router would pick it: the most specific path matching
+    /// `path`, a nested service serving everything under its path, or else the fallback.
+    /// The path is picked before the method, so a path served for other methods only matches nothing,
+    /// as axum answers `405 Method Not Allowed`, even when a less specific path serves the method.
+    /// A `HEAD` request matches the `GET` routes as well.
+    pub fn match_path(&self, method: &str, path: &str) -> Option<RouteMatch<'_>> {
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        let matches: Vec<(Vec<SegmentRank>, RouteMatch<'_>)> = self.routes.iter().filter_map(|route| {
+            let (ranks, params) = match route.kind {
+                RouteKind::Fallback => return None,
+                RouteKind::Service => match_template(&format!("{}/{{*__rest}}", route.path.trim_end_matches('/')), path)
+                    .or_else(|| match_template(&route.path, path))
+                    .map(|(mut ranks, _)| {
+                        ranks.push(SegmentRank::Wildcard);
+                        (ranks, vec![])
+                    })?,
+                RouteKind::Handler | RouteKind::WebSocket | RouteKind::Sse => match_template(&route.path, path)?,
+            };
+            Some((ranks, RouteMatch { route, params }))
+        }).collect();
+
+        let Some(best) = matches.iter().map(|(ranks, _)| ranks).max().cloned() else {
+            return self.routes.iter()
+                .find(|route| route.kind == RouteKind::Fallback)
+                .map(|route| RouteMatch { route, params: vec![] });
+        };
+        matches.into_iter()
+            .filter(|(ranks, _)| *ranks == best)
+            .map(|(_, found)| found)
+            .find(|found| serves(found.route, method))
+    }
+}
+
+/// whether the route serves the method, a nested service serves them all
+fn serves(route: &RouteMethodDesc, method: &str) -> bool {
+    route.kind == RouteKind::Service || route.http_methods.iter().any(|it| {
+        it.eq_ignore_ascii_case(method) || (method.eq_ignore_ascii_case("head") && it.eq_ignore_ascii_case("get"))
+    })
+}
+
+impl Default for RouteRegistry {
+    fn default() -> Self {
+        RouteRegistry::new()
+    }
+}
+
+/// the rank of each matched segment and the placeholder values
+type TemplateMatch = (Vec<SegmentRank>, Vec<(String, String)>);
+
+/// match a path against a template
+fn match_template(template: &str, path: &str) -> Option<TemplateMatch> {
+    let mut ranks = vec![];
+    let mut params = vec![];
+    let mut path_segments = path.split('/');
+    let mut template_segments = template.split('/');
+    loop {
+        match (template_segments.next(), path_segments.next()) {
+            (None, None) => return Some((ranks, params)),
+            (Some(template_segment), Some(path_segment)) => {
+                let (prefix, placeholder, suffix) = split_placeholder(template_segment);
+                let Some(placeholder) = placeholder else {
+                    if unescape(template_segment) != path_segment {
+                        return None;
+                    }
+                    ranks.push(SegmentRank::Static);
+                    continue;
+                };
+                if let Some(name) = placeholder.strip_prefix('*') {
+                    // a wildcard takes the rest of the path, which can't be empty
+                    let rest: Vec<&str> = std::iter::once(path_segment).chain(path_segments).collect();
+                    let rest = rest.join("/");
+                    if rest.is_empty() {
+                        return None;
+                    }
+                    ranks.push(SegmentRank::Wildcard);
+                    params.push((name.to_string(), percent_decode(&rest)));
+                    return Some((ranks, params));
+                }
+                let value = path_segment.strip_prefix(prefix.as_str())?.strip_suffix(suffix.as_str())?;
+                if value.is_empty() {
+                    return None;
+                }
+                ranks.push(SegmentRank::Placeholder);
+                params.push((placeholder.to_string(), percent_decode(value)));
+            },
+            _ => return None,
+        }
+    }
+}
+
+/// split a template segment like `{id}.json` around its placeholder, unescaping `{{` and `}}`
+fn split_placeholder(segment: &str) -> (String, Option<String>, String) {
+    let mut prefix = String::new();
+    let mut placeholder: Option<String> = None;
+    let mut suffix = String::new();
+    let mut chars = segment.chars().peekable();
+    while let Some(c) = chars.next() {
+        let text = if placeholder.is_some() { &mut suffix } else { &mut prefix };
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            },
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            },
+            '{' if placeholder.is_none() => placeholder = Some(chars.by_ref().take_while(|c| *c != '}').collect()),
+            c => text.push(c),
+        }
+    }
+    (prefix, placeholder, suffix)
+}
+
+fn unescape(segment: &str) -> String {
+    segment.replace("{{", "{").replace("}}", "}")
+}
+
+/// decode `%XX` escapes, invalid UTF-8 is replaced
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = bytes.get(index + 1..index + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[index], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            },
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            },
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(method: &str, path: &str, fn_name: &str) -> RouteMethodDesc {
+        RouteMethodDesc::new("product".to_string(), path.to_string(), method.to_string(), fn_name.to_string(), String::new(), String::new(), String::new())
+    }
+
+    fn matched(registry: &RouteRegistry, method: &str, path: &str) -> Option<(String, Vec<(String, String)>)> {
+        registry.match_path(method, path).map(|found| (found.route.fn_name.clone(), found.params))
+    }
+
+    #[test]
+    fn matches_placeholders() {
+        let registry = RouteRegistry::from_routes(vec![route("get", "/api/product/{id}/{action}", "get_product")]);
+        assert_eq!(matched(&registry, "GET", "/api/product/42/view?full=1"), Some(("get_product".to_string(), vec![
+            ("id".to_string(), "42".to_string()),
+            ("action".to_string(), "view".to_string()),
+        ])));
+        assert_eq!(matched(&registry, "GET", "/api/product/42"), None);
+    }
+
+    #[test]
+    fn prefers_static_segments_over_placeholders_over_wildcards() {
+        let registry = RouteRegistry::from_routes(vec![
+            route("get", "/files/{*rest}", "any_file"),
+            route("get", "/files/{name}", "file"),
+            route("get", "/files/readme", "readme"),
+        ]);
+        assert_eq!(matched(&registry, "GET", "/files/readme").unwrap().0, "readme");
+        assert_eq!(matched(&registry, "GET", "/files/notes").unwrap().0, "file");
+        assert_eq!(matched(&registry, "GET", "/files/notes/today").unwrap(), ("any_file".to_string(), vec![("rest".to_string(), "notes/today".to_string())]));
+    }
+
+    #[test]
+    fn a_more_specific_path_of_other_methods_matches_nothing() {
+        let registry = RouteRegistry::from_routes(vec![
+            route("post", "/api/product/new", "new_product"),
+            route("get", "/api/product/{id}", "get_product"),
+        ]);
+        assert_eq!(matched(&registry, "POST", "/api/product/new").unwrap().0, "new_product");
+        assert_eq!(matched(&registry, "GET", "/api/product/new"), None);
+        assert_eq!(matched(&registry, "GET", "/api/product/42").unwrap().0, "get_product");
+    }
+
+    #[test]
+    fn head_matches_get_routes() {
+        let registry = RouteRegistry::from_routes(vec![route("get", "/health", "health")]);
+        assert_eq!(matched(&registry, "HEAD", "/health").unwrap().0, "health");
+        assert_eq!(matched(&registry, "POST", "/health"), None);
+    }
+
+    #[test]
+    fn services_take_their_path_and_the_fallback_the_rest() {
+        let registry = RouteRegistry::from_routes(vec![
+            route("any", "/assets", "assets").with_kind(RouteKind::Service),
+            route("any", "/", "not_found").with_kind(RouteKind::Fallback),
+            route("get", "/api/product/{id}", "get_product"),
+        ]);
+        assert_eq!(matched(&registry, "GET", "/assets/css/site.css").unwrap().0, "assets");
+        assert_eq!(matched(&registry, "DELETE", "/assets").unwrap().0, "assets");
+        assert_eq!(matched(&registry, "GET", "/unknown").unwrap().0, "not_found");
+        assert_eq!(matched(&registry, "POST", "/api/product/42"), None);
+    }
+
+    #[test]
+    fn decodes_params() {
+        let registry = RouteRegistry::from_routes(vec![route("get", "/tags/{tag}.json", "tag")]);
+        assert_eq!(matched(&registry, "GET", "/tags/caf%C3%A9.json").unwrap().1, vec![("tag".to_string(), "café".to_string())]);
+    }
+
+    #[test]
+    fn finds_routes_by_name() {
+        let registry = RouteRegistry::from_routes(vec![route("get", "/api/product/{id}", "get_product").with_name("product.detail".to_string())]);
+        assert_eq!(registry.by_name("product.detail").map(|route| route.fn_name.as_str()), Some("get_product"));
+        assert!(registry.by_name("product.list").is_none());
+    }
+}
@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+use crate::{RouteKind, RouteMethodDesc, RouteRegistry};
+
+/// How `format_route_table` renders the routes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RouteTableFormat {
+    /// aligned columns for a terminal or a log
+    #[default]
+    Text,
+    /// a Markdown section and table per module
+    Markdown,
+}
+
+const HEADERS: [&str; 5] = ["Method", "Path", "Handler", "Source", "Tags"];
+
+/// The registered routes as a table grouped by module, with the method, path, handler,
+/// source location and tags of each route:
+///
+/// ```text
+/// shop::product
+///   Method  Path                        Handler       Source          Tags
+///   POST    /api/product/save           save_product  src/shop.rs:21  public, admin
+///   GET     /api/product/{id}/{action}  get_product   src/shop.rs:16  public
+/// ```
+pub fn format_route_table(format: RouteTableFormat) -> String {
+    format_routes_table(RouteRegistry::global().routes(), format)
+}
+
+/// print the table of `format_route_table`, e.g. when the server starts
+pub fn print_route_table(format: RouteTableFormat) {
+    print!("{}", format_route_table(format));
+}
+
+/// the table of `format_route_table` for the given routes, e.g. those of `RouteRegistry::by_tag`
+pub fn format_routes_table<'a>(routes: impl IntoIterator<Item = &'a RouteMethodDesc>, format: RouteTableFormat) -> String {
+    // the modules in order, their routes in the order they're given
+    let mut modules: BTreeMap<&str, Vec<[String; 5]>> = BTreeMap::new();
+    for route in routes {
+        let module = if route.module_path.is_empty() { &route.mod_name } else { &route.module_path };
+        modules.entry(module).or_default().push(row(route));
+    }
+
+    // one width per column across modules, so the tables line up
+    let mut widths = HEADERS.map(|header| header.chars().count());
+    for row in modules.values().flatten() {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell_text(cell, format).chars().count());
+        }
+    }
+
+    let mut table = String::new();
+    for (index, (module, rows)) in modules.iter().enumerate() {
+        if index > 0 {
+            table.push('\n');
+        }
+        match format {
+            RouteTableFormat::Text => {
+                table.push_str(&format!("{}\n", module));
+                table.push_str(&text_line(&HEADERS.map(String::from), &widths));
+                for row in rows {
+                    table.push_str(&text_line(row, &widths));
+                }
+            },
+            RouteTableFormat::Markdown => {
+                table.push_str(&format!("### `{}`\n\n", module));
+                table.push_str(&markdown_line(&HEADERS.map(String::from), &widths));
+                table.push_str(&markdown_line(&widths.map(|width| "-".repeat(width)), &widths));
+                for row in rows {
+                    let cells = row.clone().map(|cell| cell_text(&cell, format));
+                    table.push_str(&markdown_line(&cells, &widths));
+                }
+            },
+        }
+    }
+    table
+}
+
+/// the cells of a route, a fallback takes any method on any other path and a nested service any method under its path
+fn row(route: &RouteMethodDesc) -> [String; 5] {
+    let (method, path) = match route.kind {
+        RouteKind::Fallback => ("ANY".to_string(), "(fallback)".to_string()),
+        RouteKind::Service => ("ANY".to_string(), format!("{}/*", route.path.trim_end_matches('/'))),
+        RouteKind::Handler | RouteKind::WebSocket | RouteKind::Sse => {
+            let methods: Vec<String> = route.http_methods.iter().map(|method| method.to_uppercase()).collect();
+            (methods.join(","), route.path.clone())
+        },
+    };
+    let handler = match &route.self_type {
+        Some(self_type) => format!("{}::{}", self_type, route.fn_name),
+        None => route.fn_name.clone(),
+    };
+    [method, path, handler, route.source_location(), route.tags.join(", ")]
+}
+
+/// a cell as written, Markdown escaping the pipes that would split it
+fn cell_text(cell: &str, format: RouteTableFormat) -> String {
+    match format {
+        RouteTableFormat::Text => cell.to_string(),
+        RouteTableFormat::Markdown => cell.replace('|', "\\|"),
+    }
+}
+
+fn text_line(cells: &[String; 5], widths: &[usize; 5]) -> String {
+    let cells: Vec<String> = cells.iter().zip(widths).map(|(cell, width)| format!("{:<width$}", cell, width = width)).collect();
+    format!("  {}\n", cells.join("  ").trim_end())
+}
+
+fn markdown_line(cells: &[String; 5], widths: &[usize; 5]) -> String {
+    let cells: Vec<String> = cells.iter().zip(widths).map(|(cell, width)| format!("{:<width$}", cell, width = width)).collect();
+    format!("| {} |\n", cells.join(" | "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn routes() -> Vec<RouteMethodDesc> {
+        let route = |mod_name: &str, method: &str, path: &str, fn_name: &str| {
+            RouteMethodDesc::new(mod_name.to_string(), path.to_string(), method.to_string(), fn_name.to_string(), String::new(), String::new(), String::new())
+        };
+        vec![
+            route("product", "post", "/api/product/save", "save")
+                .with_module_path("shop::product".to_string())
+                .with_self_type("ProductController".to_string())
+                .with_source_location("src/shop.rs".to_string(), 21)
+                .with_tags(vec!["public".to_string(), "admin".to_string()]),
+            route("product", "get", "/api/product/{id}", "get_product")
+                .with_module_path("shop::product".to_string())
+                .with_http_methods(vec!["get".to_string(), "head".to_string()])
+                .with_source_location("src/shop.rs".to_string(), 16)
+                .with_tags(vec!["a|b".to_string()]),
+            route("main", "any", "/assets/", "assets").with_kind(RouteKind::Service),
+            route("main", "any", "/", "not_found")
+                .with_kind(RouteKind::Fallback)
+                .with_source_location("src/main.rs".to_string(), 40),
+        ]
+    }
+
+    #[test]
+    fn formats_text() {
+        assert_eq!(format_routes_table(&routes(), RouteTableFormat::Text), concat!(
+            "main\n",
+            "  Method    Path               Handler                  Source          Tags\n",
+            "  ANY       /assets/*          assets\n",
+            "  ANY       (fallback)         not_found                src/main.rs:40\n",
+            "\n",
+            "shop::product\n",
+            "  Method    Path               Handler                  Source          Tags\n",
+            "  POST      /api/product/save  ProductController::save  src/shop.rs:21  public, admin\n",
+            "  GET,HEAD  /api/product/{id}  get_product              src/shop.rs:16  a|b\n",
+        ));
+    }
+
+    #[test]
+    fn formats_markdown() {
+        assert_eq!(format_routes_table(&routes(), RouteTableFormat::Markdown), concat!(
+            "### `main`\n",
+            "\n",
+            "| Method   | Path              | Handler                 | Source         | Tags          |\n",
+            "| -------- | ----------------- | ----------------------- | -------------- | ------------- |\n",
+            "| ANY      | /assets/*         | assets                  |                |               |\n",
+            "| ANY      | (fallback)        | not_found               | src/main.rs:40 |               |\n",
+            "\n",
+            "### `shop::product`\n",
+            "\n",
+            "| Method   | Path              | Handler                 | Source         | Tags          |\n",
+            "| -------- | ----------------- | ----------------------- | -------------- | ------------- |\n",
+            "| POST     | /api/product/save | ProductController::save | src/shop.rs:21 | public, admin |\n",
+            "| GET,HEAD | /api/product/{id} | get_product             | src/shop.rs:16 | a\\|b          |\n",
+        ));
+    }
+
+    #[test]
+    fn formats_no_routes_as_nothing() {
+        assert_eq!(format_routes_table(&[], RouteTableFormat::Markdown), "");
+    }
+}
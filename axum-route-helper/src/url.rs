@@ -0,0 +1,157 @@
+use std::fmt;
+use crate::{RouteMethodDesc, RouteRegistry};
+
+/// Why a URL couldn't be built by `url_for`
+#[derive(Debug,Clone,PartialEq)]
+pub enum UrlForError {
+    /// no route is registered under the name
+    UnknownRoute(String),
+    /// a placeholder of the route path has no param
+    MissingParam { route: String, param: String },
+    /// a param isn't a placeholder of the route path
+    ExtraParam { route: String, param: String },
+}
+
+impl fmt::Display for UrlForError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UrlForError::UnknownRoute(route) => write!(f, "no route named `{}`", route),
+            UrlForError::MissingParam { route, param } => write!(f, "missing param `{}` for route `{}`", param, route),
+            UrlForError::ExtraParam { route, param } => write!(f, "route `{}` has no placeholder `{}`", route, param),
+        }
+    }
+}
+
+impl std::error::Error for UrlForError {}
+
+/// Build the URL of the route named by `#[route(..., name = "product.detail")]`,
+/// filling the `{placeholders}` of its path from `params`:
+///
+/// ```rust,ignore
+/// let url = url_for("product.detail", [("id", 42)])?; // "/api/product/42"
+/// ```
+///
+/// Values are percent-encoded, except for the `/` of a `{*wildcard}`.
+pub fn url_for<I, K, V>(name: &str, params: I) -> Result<String, UrlForError>
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<str>,
+    V: ToString,
+{
+    let desc = RouteRegistry::global().by_name(name)
+        .ok_or_else(|| UrlForError::UnknownRoute(name.to_string()))?;
+    fill_path(desc, params)
+}
+
+/// fill the placeholders of the route's path
+pub fn fill_path<I, K, V>(desc: &RouteMethodDesc, params: I) -> Result<String, UrlForError>
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<str>,
+    V: ToString,
+{
+    let route = desc.name.clone().unwrap_or_else(|| desc.path.clone());
+    let mut params: Vec<(String, String)> = params.into_iter()
+        .map(|(key, value)| (key.as_ref().to_string(), value.to_string()))
+        .collect();
+
+    let mut url = String::new();
+    let mut chars = desc.path.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                url.push('{');
+            },
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                url.push('}');
+            },
+            '{' => {
+                let placeholder: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                let (param, is_wildcard) = match placeholder.strip_prefix('*') {
+                    Some(param) => (param.to_string(), true),
+                    None => (placeholder, false),
+                };
+                let index = params.iter().position(|(key, _)| *key == param)
+                    .ok_or_else(|| UrlForError::MissingParam { route: route.clone(), param: param.clone() })?;
+                let (_, value) = params.remove(index);
+                url.push_str(&percent_encode(&value, is_wildcard));
+            },
+            c => url.push(c),
+        }
+    }
+
+    match params.into_iter().next() {
+        Some((param, _)) => Err(UrlForError::ExtraParam { route, param }),
+        None => Ok(url),
+    }
+}
+
+/// percent-encode a path segment, keeping `/` for wildcards
+fn percent_encode(value: &str, keep_slash: bool) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => encoded.push(byte as char),
+            b'/' if keep_slash => encoded.push('/'),
+            byte => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(path: &str) -> RouteMethodDesc {
+        RouteMethodDesc::new("product".to_string(), path.to_string(), "get".to_string(), "get_product".to_string(), String::new(), String::new(), String::new())
+            .with_name("product.detail".to_string())
+    }
+
+    #[test]
+    fn fills_placeholders() {
+        let url = fill_path(&route("/api/product/{id}/{action}"), [("action", "view"), ("id", "42")]);
+        assert_eq!(url, Ok("/api/product/42/view".to_string()));
+    }
+
+    #[test]
+    fn takes_any_displayable_value() {
+        assert_eq!(fill_path(&route("/api/product/{id}"), [("id", 42)]), Ok("/api/product/42".to_string()));
+    }
+
+    #[test]
+    fn fails_on_a_missing_param() {
+        let url = fill_path(&route("/api/product/{id}/{action}"), [("id", "42")]);
+        assert_eq!(url, Err(UrlForError::MissingParam { route: "product.detail".to_string(), param: "action".to_string() }));
+    }
+
+    #[test]
+    fn fails_on_an_extra_param() {
+        let url = fill_path(&route("/api/product/{id}"), [("id", "42"), ("page", "2")]);
+        assert_eq!(url, Err(UrlForError::ExtraParam { route: "product.detail".to_string(), param: "page".to_string() }));
+    }
+
+    #[test]
+    fn fails_on_an_unknown_name() {
+        assert_eq!(url_for("nope", [("id", "42")]), Err(UrlForError::UnknownRoute("nope".to_string())));
+    }
+
+    #[test]
+    fn percent_encodes_values() {
+        let url = fill_path(&route("/api/product/{id}"), [("id", "4 2/ü?")]);
+        assert_eq!(url, Ok("/api/product/4%202%2F%C3%BC%3F".to_string()));
+    }
+
+    #[test]
+    fn keeps_the_slashes_of_a_wildcard() {
+        let url = fill_path(&route("/files/{*rest}"), [("rest", "a/b c")]);
+        assert_eq!(url, Ok("/files/a/b%20c".to_string()));
+    }
+
+    #[test]
+    fn unescapes_braces() {
+        assert_eq!(fill_path(&route("/{{raw}}/{id}"), [("id", "1")]), Ok("/{raw}/1".to_string()));
+    }
+}
@@ -0,0 +1,101 @@
+use proc_macro::TokenStream;
+use quote::{quote, ToTokens};
+use syn::{FnArg, ImplItem, ItemFn, ItemImpl, Meta};
+use crate::handlers::HandlersDef;
+use crate::{expand_route_fn, is_route_attr, UseCollector, ROUTE_ATTRS};
+
+/// Register the routes of the associated fns of `#[handlers] impl ProductController { ... }`.
+/// The route attributes are taken off the fns, which stay in the impl block, and expanded next to it
+/// as the impl can't hold the route providers.
+pub fn expand_impl(handlers_def: HandlersDef, mut item_impl: ItemImpl) -> TokenStream {
+    if !item_impl.generics.params.is_empty() || item_impl.trait_.is_some() {
+        return syn::Error::new_spanned(
+            &item_impl.self_ty,
+            "#[handlers] expects an inherent impl block of a type without generics",
+        ).into_compile_error().into();
+    }
+    let self_ty = item_impl.self_ty.as_ref().clone();
+
+    let collector = UseCollector {
+        mod_name: to_snake_case(&type_name(&self_ty)),
+        uses: Vec::new(),
+        prefix: handlers_def.prefix,
+        version: handlers_def.version,
+        layers: handlers_def.layers,
+        state: handlers_def.state,
+        tags: handlers_def.tags,
+    };
+
+    let mut routes = vec![];
+    for item in item_impl.items.iter_mut() {
+        let ImplItem::Fn(impl_fn) = item else {
+            continue;
+        };
+        let (mut route_attrs, attrs) = impl_fn.attrs.drain(..).partition(is_route_attr);
+        impl_fn.attrs = attrs;
+        if route_attrs.is_empty() {
+            continue;
+        }
+        if let Some(FnArg::Receiver(receiver)) = impl_fn.sig.inputs.first() {
+            routes.push(syn::Error::new_spanned(receiver, "A handler can't take `self`, make it an associated fn.").into_compile_error());
+            continue;
+        }
+
+        let input_fn = ItemFn {
+            attrs: impl_fn.attrs.clone(),
+            vis: impl_fn.vis.clone(),
+            sig: impl_fn.sig.clone(),
+            block: Box::new(impl_fn.block.clone()),
+        };
+        for attr in route_attrs.iter_mut() {
+            let name = attr.path().segments.last().map(|segment| segment.ident.to_string()).unwrap_or_default();
+            let implied_method = match name.as_str() {
+                "route" => None,
+                "fallback" | "nest_service" => {
+                    routes.push(syn::Error::new_spanned(&attr, format!("#[{}] isn't supported in impl blocks, use a free fn.", name)).into_compile_error());
+                    continue;
+                },
+                name => ROUTE_ATTRS.iter().copied().find(|method| *method == name),
+            };
+            collector.inject_into(attr);
+            let Meta::List(list) = &attr.meta else {
+                routes.push(syn::Error::new_spanned(&attr, "Expected the route arguments like #[route(\"/path\", method = \"get\")].").into_compile_error());
+                continue;
+            };
+            let route = expand_route_fn(TokenStream::from(list.tokens.clone()), input_fn.clone(), implied_method, Some(&self_ty));
+            routes.push(route.into());
+        }
+    }
+
+    TokenStream::from(quote! {
+        #item_impl
+
+        #(#routes)*
+    })
+}
+
+/// the name of the type without its path and generics, e.g. `ProductController`
+pub fn type_name(ty: &syn::Type) -> String {
+    match ty {
+        syn::Type::Path(type_path) => type_path.path.segments.last()
+            .map(|segment| segment.ident.to_string())
+            .unwrap_or_default(),
+        ty => ty.to_token_stream().to_string().replace(|c: char| !c.is_alphanumeric(), ""),
+    }
+}
+
+/// `ProductController` as `product_controller`
+fn to_snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+    for (index, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if index > 0 {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
@@ -0,0 +1,80 @@
+//! Opt-in dump of what `#[route]` expanded, one JSON file per route.
+//!
+//! Enabled by the `debug` route option or by setting `AXUM_ROUTE_DEBUG=1` for the build;
+//! cargo doesn't rebuild on env changes alone, so `touch` a source file or `cargo clean -p` the crate.
+//! The files go to `AXUM_ROUTE_DEBUG_DIR` if set, else under `OUT_DIR` or the target directory:
+//! `<dir>/axum-route-debug/<crate>/<fn>-<hash>.json`.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::{env, fs};
+
+const DEBUG_ENV: &str = "AXUM_ROUTE_DEBUG";
+const DEBUG_DIR_ENV: &str = "AXUM_ROUTE_DEBUG_DIR";
+
+/// whether to dump the route, from its `debug` option or the `AXUM_ROUTE_DEBUG` env var
+pub fn enabled(debug_option: bool) -> bool {
+    debug_option || env::var(DEBUG_ENV).is_ok_and(|value| !value.is_empty() && value != "0" && value != "false")
+}
+
+/// A JSON object, built field by field
+#[derive(Debug, Default)]
+pub struct Record {
+    fields: Vec<String>,
+}
+
+impl Record {
+    pub fn str(mut self, key: &str, value: &str) -> Self {
+        self.fields.push(format!("{}:{}", json_string(key), json_string(value)));
+        self
+    }
+
+    pub fn list(mut self, key: &str, values: &[String]) -> Self {
+        let values: Vec<String> = values.iter().map(|value| json_string(value)).collect();
+        self.fields.push(format!("{}:[{}]", json_string(key), values.join(",")));
+        self
+    }
+
+    pub fn to_json(&self) -> String {
+        format!("{{{}}}\n", self.fields.join(","))
+    }
+
+    /// write the record of the route `fn_name`, `key` tells apart same-named handlers
+    pub fn write(&self, fn_name: &str, key: &str) -> syn::Result<()> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let dir = debug_dir().join(env::var("CARGO_PKG_NAME").unwrap_or_default());
+        let file = dir.join(format!("{}-{:08x}.json", fn_name, hasher.finish() as u32));
+        fs::create_dir_all(&dir)
+            .and_then(|_| fs::write(&file, self.to_json()))
+            .map_err(|err| syn::Error::new(proc_macro2::Span::call_site(), format!("Can't write the route debug file {}: {}", file.display(), err)))
+    }
+}
+
+/// `AXUM_ROUTE_DEBUG_DIR`, `OUT_DIR`, `CARGO_TARGET_DIR` or the `target` directory of the crate
+fn debug_dir() -> PathBuf {
+    let dir = [DEBUG_DIR_ENV, "OUT_DIR", "CARGO_TARGET_DIR"].iter()
+        .find_map(|name| env::var(name).ok().filter(|dir| !dir.is_empty()))
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap_or_default()).join("target"));
+    dir.join("axum-route-debug")
+}
+
+fn json_string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
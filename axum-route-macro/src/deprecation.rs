@@ -0,0 +1,196 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::LitStr;
+use crate::options::RouteOptions;
+
+const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// The `deprecated`, `deprecated_since` and `sunset` options of a route
+#[derive(Debug, Default)]
+pub struct Deprecation {
+    /// the note of `deprecated = "use /v2/product"`, empty for a bare `deprecated`
+    pub note: Option<String>,
+    /// the date of `sunset = "2027-01-01"`
+    pub sunset: Option<String>,
+    /// the `Deprecation` header, the date the route is deprecated since as `@<unix-seconds>`
+    deprecation_header: Option<String>,
+    /// the `Sunset` header, the sunset as an HTTP-date, e.g. `Fri, 01 Jan 2027 00:00:00 GMT`
+    sunset_header: Option<String>,
+}
+
+impl Deprecation {
+    /// take the `deprecated`, `deprecated_since` and `sunset` options,
+    /// a route is deprecated since its `deprecated_since` date, or else since its sunset
+    pub fn take_from(options: &mut RouteOptions) -> syn::Result<Deprecation> {
+        let mut deprecation = Deprecation::default();
+        if let Some(option) = options.take("deprecated") {
+            deprecation.note = match option.as_str() {
+                Ok(note) => Some(note.value()),
+                Err(_) => option.as_bool()?.then(String::new),
+            };
+        }
+        let since = match options.take_str("deprecated_since")? {
+            Some(since) => {
+                deprecation.note.get_or_insert_with(String::new);
+                Some(days_since_epoch_of(&since, "deprecated_since")?)
+            },
+            None => None,
+        };
+        let sunset = match options.take_str("sunset")? {
+            Some(sunset) => {
+                deprecation.sunset = Some(sunset.value());
+                Some(days_since_epoch_of(&sunset, "sunset")?)
+            },
+            None => None,
+        };
+        deprecation.sunset_header = sunset.map(http_date);
+        if deprecation.note.is_some() {
+            deprecation.deprecation_header = since.or(sunset).map(structured_date);
+        }
+        Ok(deprecation)
+    }
+
+    /// a layer adding the `Deprecation` and `Sunset` headers to the responses of the route,
+    /// a route deprecated without any date gets no `Deprecation` header as RFC 9745 wants a date
+    pub fn layer(&self) -> Option<TokenStream2> {
+        if self.deprecation_header.is_none() && self.sunset_header.is_none() {
+            return None;
+        }
+        let deprecation = self.deprecation_header.as_ref().map(|deprecation| quote! {
+            response.headers_mut().insert(
+                axum::http::HeaderName::from_static("deprecation"),
+                axum::http::HeaderValue::from_static(#deprecation),
+            );
+        });
+        let sunset = self.sunset_header.as_ref().map(|sunset| quote! {
+            response.headers_mut().insert(
+                axum::http::HeaderName::from_static("sunset"),
+                axum::http::HeaderValue::from_static(#sunset),
+            );
+        });
+        Some(quote! {
+            axum::middleware::map_response(|mut response: axum::response::Response| async move {
+                #deprecation
+                #sunset
+                response
+            })
+        })
+    }
+}
+
+/// the days since 1970-01-01 of a `YYYY-MM-DD` date given to `option`
+fn days_since_epoch_of(date: &LitStr, option: &str) -> syn::Result<i64> {
+    let error = || syn::Error::new(date.span(), format!("Invalid {} date `{}`, expected a date like \"2027-01-01\".", option, date.value()));
+    let value = date.value();
+    let parts: Vec<&str> = value.trim().split('-').collect();
+    let [year, month, day] = parts.as_slice() else {
+        return Err(error());
+    };
+    if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return Err(error());
+    }
+    let year: i64 = year.parse().map_err(|_| error())?;
+    let month: i64 = month.parse().map_err(|_| error())?;
+    let day: i64 = day.parse().map_err(|_| error())?;
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let month_days = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return Err(error()),
+    };
+    if day < 1 || day > month_days {
+        return Err(error());
+    }
+    Ok(days_since_epoch(year, month, day))
+}
+
+/// the HTTP-date of midnight UTC on the day, as the `Sunset` header wants it
+fn http_date(days: i64) -> String {
+    let weekday = WEEKDAYS[days.rem_euclid(7) as usize];
+    let (year, month, day) = civil_date(days);
+    format!("{}, {:02} {} {} 00:00:00 GMT", weekday, day, MONTHS[month as usize - 1], year)
+}
+
+/// the structured field date of midnight UTC on the day, as the `Deprecation` header wants it, e.g. `@1798761600`
+fn structured_date(days: i64) -> String {
+    format!("@{}", days * 86_400)
+}
+
+/// days from 1970-01-01 to the date of the proleptic Gregorian calendar
+fn days_since_epoch(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// the year, month and day of the days since 1970-01-01, the inverse of `days_since_epoch`
+fn civil_date(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn days(date: &str) -> syn::Result<i64> {
+        days_since_epoch_of(&LitStr::new(date, proc_macro2::Span::call_site()), "sunset")
+    }
+
+    #[test]
+    fn formats_http_dates() {
+        assert_eq!(http_date(days("2027-01-01").unwrap()), "Fri, 01 Jan 2027 00:00:00 GMT");
+        assert_eq!(http_date(days("1970-01-01").unwrap()), "Thu, 01 Jan 1970 00:00:00 GMT");
+        assert_eq!(http_date(days("2000-03-01").unwrap()), "Wed, 01 Mar 2000 00:00:00 GMT");
+    }
+
+    #[test]
+    fn formats_leap_days() {
+        assert_eq!(http_date(days("2028-02-29").unwrap()), "Tue, 29 Feb 2028 00:00:00 GMT");
+        assert_eq!(http_date(days("2000-02-29").unwrap()), "Tue, 29 Feb 2000 00:00:00 GMT");
+    }
+
+    #[test]
+    fn formats_structured_dates() {
+        assert_eq!(structured_date(days("2027-01-01").unwrap()), "@1798761600");
+        assert_eq!(structured_date(days("1970-01-01").unwrap()), "@0");
+    }
+
+    #[test]
+    fn rejects_invalid_dates() {
+        for date in ["2027-02-29", "1900-02-29", "2027-13-01", "2027-04-31", "2027-00-10", "2027-1-1", "01-01-2027", "soon"] {
+            assert!(days(date).is_err(), "{} should be invalid", date);
+        }
+    }
+
+    #[test]
+    fn deprecated_since_takes_precedence_over_the_sunset() {
+        let mut options: RouteOptions = syn::parse2(quote! { deprecated_since = "2026-01-01", sunset = "2027-01-01" }).unwrap();
+        let deprecation = Deprecation::take_from(&mut options).unwrap();
+        assert_eq!(deprecation.note.as_deref(), Some(""));
+        assert_eq!(deprecation.deprecation_header.as_deref(), Some("@1767225600"));
+        assert_eq!(deprecation.sunset_header.as_deref(), Some("Fri, 01 Jan 2027 00:00:00 GMT"));
+    }
+
+    #[test]
+    fn a_deprecation_without_a_date_has_no_header() {
+        let mut options: RouteOptions = syn::parse2(quote! { deprecated = "use /v2/product" }).unwrap();
+        let deprecation = Deprecation::take_from(&mut options).unwrap();
+        assert!(deprecation.deprecation_header.is_none());
+        assert!(deprecation.layer().is_none());
+    }
+}
@@ -0,0 +1,72 @@
+use quote::ToTokens;
+use crate::options::RouteOptions;
+use crate::{layer, route_path};
+
+/// the options `#[handlers(...)]` knows
+const HANDLERS_OPTIONS: [&str; 5] = ["prefix", "version", "layer", "state", "tags"];
+
+/// Options of `#[handlers(...)]`, applied to every route of the module
+#[derive(Debug, Default)]
+pub struct HandlersDef {
+    /// path prepended to every route of the module, e.g. `prefix = "/api/product"`
+    pub prefix: String,
+    /// api version of every route of the module, e.g. `version = "v1"`, prepended before the prefix
+    pub version: String,
+    /// middleware functions or layer expressions wrapping every route of the module, outermost first
+    pub layers: Vec<String>,
+    /// state type of the routers the module's routes are added to, e.g. `state = crate::AppState`
+    pub state: String,
+    /// tags of every route of the module, e.g. `tags = ["admin"]`
+    pub tags: Vec<String>,
+}
+
+impl syn::parse::Parse for HandlersDef {
+    fn parse(input: syn::parse::ParseStream<'_>) -> syn::Result<Self> {
+        let mut options: RouteOptions = input.parse()?;
+        let mut handlers_def = HandlersDef::default();
+
+        if let Some(lit_str) = options.take_str("prefix")? {
+            let prefix = lit_str.value();
+            if !prefix.starts_with('/') {
+                return Err(syn::Error::new(lit_str.span(), "The prefix has to start with `/`."));
+            }
+            route_path::parse_placeholders(&prefix, lit_str.span())?;
+            handlers_def.prefix = prefix.trim_end_matches('/').to_string();
+        }
+
+        if let Some(version) = options.take_str("version")? {
+            handlers_def.version = route_path::parse_version(&version)?;
+        }
+
+        for lit_str in options.take_str_list("layer")? {
+            layer::parse_layer(&lit_str.value(), lit_str.span())?;
+            // kept on one line as the layers are handed to the routes line by line
+            handlers_def.layers.push(lit_str.value().lines().map(str::trim).collect::<Vec<_>>().join(" "));
+        }
+
+        // a bare type path, or a string for types that aren't paths like `Arc<AppState>`
+        if let Some(state) = options.take_type("state")? {
+            handlers_def.state = state.to_token_stream().to_string();
+        }
+
+        handlers_def.tags = parse_tags(options.take_str_list("tags")?)?;
+
+        options.ensure_empty(&HANDLERS_OPTIONS)?;
+        Ok(handlers_def)
+    }
+}
+
+/// the values of a `tags = [...]` option, which can't be empty or contain line breaks
+pub fn parse_tags(tags: Vec<syn::LitStr>) -> syn::Result<Vec<String>> {
+    let mut values: Vec<String> = vec![];
+    for tag in tags {
+        let value = tag.value().trim().to_string();
+        if value.is_empty() || value.contains('\n') {
+            return Err(syn::Error::new(tag.span(), "A tag can't be empty or span several lines."));
+        }
+        if !values.contains(&value) {
+            values.push(value);
+        }
+    }
+    Ok(values)
+}
@@ -0,0 +1,110 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, ToTokens};
+use syn::{FnArg, ItemFn};
+use crate::is_named;
+use crate::options::RouteOptions;
+
+/// What a `#[route]` handler serves, from its `kind` option or else its signature
+#[derive(Debug)]
+pub enum HandlerKind {
+    /// a plain request/response handler
+    Http,
+    /// a handler taking `WebSocketUpgrade`, with the message types the client sends and receives
+    WebSocket { send: String, receive: String },
+    /// a handler returning `Sse<...>`, with the payload type of its events
+    Sse { event: String },
+}
+
+/// the values of the `kind` option
+const KINDS: [&str; 3] = ["http", "ws", "sse"];
+
+impl HandlerKind {
+    /// take the `kind`, `ws_send`, `ws_receive` and `sse_event` options, detecting a WebSocket handler
+    /// by its `WebSocketUpgrade` argument and an SSE handler by its `Sse` return type
+    pub fn take_from(options: &mut RouteOptions, input_fn: &ItemFn) -> syn::Result<HandlerKind> {
+        let kind = match options.take_str("kind")? {
+            Some(kind) => match kind.value().as_str() {
+                "http" => "http",
+                "ws" | "websocket" => "ws",
+                "sse" => "sse",
+                other => {
+                    return Err(syn::Error::new(kind.span(), format!("Unknown route kind `{}`, expected one of {}.", other, KINDS.join(", "))));
+                },
+            },
+            None if takes_arg(input_fn, "WebSocketUpgrade") => "ws",
+            None if returns(input_fn, "Sse") => "sse",
+            None => "http",
+        };
+
+        let send = options.take("ws_send");
+        let receive = options.take("ws_receive");
+        let event = options.take("sse_event");
+        if kind != "ws" {
+            if let Some(option) = send.as_ref().or(receive.as_ref()) {
+                return Err(syn::Error::new_spanned(option, "The message types are only known by WebSocket routes, see `kind = \"ws\"`."));
+            }
+        }
+        if kind != "sse" {
+            if let Some(option) = event.as_ref() {
+                return Err(syn::Error::new_spanned(option, "The event type is only known by SSE routes, see `kind = \"sse\"`."));
+            }
+        }
+        // messages and events are text unless typed, e.g. `ws_send = ClientMessage, ws_receive = ServerMessage`
+        let type_name = |option: Option<crate::options::RouteOption>| -> syn::Result<String> {
+            Ok(match option {
+                Some(option) => option.as_type()?.to_token_stream().to_string().replace(' ', ""),
+                None => "String".to_string(),
+            })
+        };
+        Ok(match kind {
+            "ws" => HandlerKind::WebSocket { send: type_name(send)?, receive: type_name(receive)? },
+            "sse" => HandlerKind::Sse { event: type_name(event)? },
+            _ => HandlerKind::Http,
+        })
+    }
+
+    /// whether the client reads the handler's response as its return type, unlike upgrades and event streams
+    pub fn is_http(&self) -> bool {
+        matches!(self, HandlerKind::Http)
+    }
+
+    /// the types the client needs imported, beside the handler's arguments and return type
+    pub fn used_types(&self) -> Vec<String> {
+        match self {
+            HandlerKind::Http => vec![],
+            HandlerKind::WebSocket { send, receive } => vec![send.clone(), receive.clone()],
+            HandlerKind::Sse { event } => vec![event.clone()],
+        }
+    }
+
+    /// the `RouteMethodDesc` setters of the kind
+    pub fn desc_setters(&self) -> TokenStream2 {
+        match self {
+            HandlerKind::Http => quote! {},
+            HandlerKind::WebSocket { send, receive } => quote! {
+                .with_kind(axum_route_helper::RouteKind::WebSocket)
+                .with_ws_messages(#send.to_string(), #receive.to_string())
+            },
+            HandlerKind::Sse { event } => quote! {
+                .with_kind(axum_route_helper::RouteKind::Sse)
+                .with_sse_event(#event.to_string())
+            },
+        }
+    }
+}
+
+/// whether an argument of the handler is of the type named `type_name`, e.g. `ws: WebSocketUpgrade`
+fn takes_arg(input_fn: &ItemFn, type_name: &str) -> bool {
+    input_fn.sig.inputs.iter().any(|arg| match arg {
+        FnArg::Typed(pat_type) => is_named(&pat_type.ty, type_name),
+        FnArg::Receiver(_) => false,
+    })
+}
+
+/// whether the handler returns the type named `type_name`, e.g. `-> Sse<impl Stream<...>>`
+fn returns(input_fn: &ItemFn, type_name: &str) -> bool {
+    match &input_fn.sig.output {
+        syn::ReturnType::Type(_, ty) => is_named(ty, type_name),
+        syn::ReturnType::Default => false,
+    }
+}
@@ -0,0 +1,22 @@
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+
+/// Parse a `layer = "..."` value into the tokens passed to `MethodRouter::layer`.
+/// A bare path like `crate::mw::require_login` names a middleware function and is wrapped in
+/// `axum::middleware::from_fn`, any other expression like `TimeoutLayer::new(..)` is used as the layer itself.
+pub fn parse_layer(layer: &str, span: Span) -> syn::Result<TokenStream2> {
+    // parsed through a literal so the tokens, and so any type errors, point at the option
+    let expr: syn::Expr = syn::LitStr::new(layer, span).parse().map_err(|err| {
+        syn::Error::new(span, format!("Expected a middleware function or a layer expression, {}", err))
+    })?;
+    Ok(match expr {
+        syn::Expr::Path(path) => quote! { axum::middleware::from_fn(#path) },
+        expr => quote! { #expr },
+    })
+}
+
+/// Chain the layers onto a `MethodRouter`, the first layer is the outermost, running first.
+pub fn apply_layers(method_router: TokenStream2, layers: &[TokenStream2]) -> TokenStream2 {
+    let layers = layers.iter().rev();
+    quote! { #method_router #(.layer(#layers))* }
+}
@@ -1,40 +1,54 @@
-#![feature(proc_macro_diagnostic)]
-#![feature(proc_macro_def_site)]
-#![feature(proc_macro_span)]
-#![feature(proc_macro_hygiene)]
-
-use quote::quote;
-use syn::{parse_macro_input, FnArg, ItemFn, Meta, MetaList, ReturnType};
-use proc_macro::{TokenStream, Span, Diagnostic, Level};
-use proc_macro::MultiSpan;
-use syn::parse::Parser;
+use quote::{quote, quote_spanned};
+use syn::{parse_macro_input, Attribute, FnArg, Item, ItemFn, Meta, ReturnType};
+use proc_macro::TokenStream;
 use crate::route::RouteDef;
-use syn::Type;
+use crate::handlers::HandlersDef;
 use quote::ToTokens;
-use syn::GenericArgument;
-use syn::PathArguments;
 use std::collections::HashMap;
-use std::marker::PhantomData;
+use crate::options::RouteOptions;
 use syn::ItemUse;
 use syn::visit::Visit;
-use syn::UseTree;
-use std::sync::{Arc, Mutex};
-use once_cell::sync::Lazy;
-use syn::ItemMod;
-use proc_macro2::Ident;
-use std::collections::HashSet;
-use std::io::BufRead;
+use proc_macro2::{Ident, TokenStream as TokenStream2, TokenTree};
+use std::collections::BTreeSet;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 
 mod route;
-
-static USE_COLLECTOR_MAP: Lazy<Mutex<HashMap<String,UseCollector>>> = Lazy::new(|| {
-    Mutex::new(HashMap::new())
-});
+mod handlers;
+mod layer;
+mod deprecation;
+mod mount;
+mod kind;
+mod controller;
+mod debug;
+mod options;
+mod route_path;
+#[cfg(feature = "nightly")]
+mod nightly;
+
+/// route options injected by `#[handlers]` into the `#[route]`s of its module
+const MOD_NAME_OPTION: &str = "__mod_name";
+const USES_OPTION: &str = "__uses";
+const PREFIX_OPTION: &str = "__prefix";
+const VERSION_OPTION: &str = "__version";
+const LAYERS_OPTION: &str = "__layers";
+const STATE_OPTION: &str = "__state";
+const TAGS_OPTION: &str = "__tags";
 
 #[derive(Debug,Clone)]
 struct UseCollector {
     mod_name:String,
     pub uses: Vec<String>,
+    /// path prefix from `#[handlers(prefix = ...)]`
+    prefix: String,
+    /// api version from `#[handlers(version = ...)]`, empty for none
+    version: String,
+    /// layers from `#[handlers(layer = ...)]`, outermost first
+    layers: Vec<String>,
+    /// router state type from `#[handlers(state = ...)]`, empty for `()`
+    state: String,
+    /// tags from `#[handlers(tags = ...)]`
+    tags: Vec<String>,
 }
 
 impl UseCollector {
@@ -42,6 +56,118 @@ impl UseCollector {
         UseCollector{
             mod_name:"".to_string(),
             uses: vec![],
+            prefix: "".to_string(),
+            version: "".to_string(),
+            layers: vec![],
+            state: "".to_string(),
+            tags: vec![],
+        }
+    }
+
+    /// take the module context `#[handlers]` injected into the route options, if any
+    fn from_route_options(options: &mut RouteOptions) -> syn::Result<Option<UseCollector>> {
+        let Some(mod_name) = options.take_str(MOD_NAME_OPTION)? else {
+            return Ok(None);
+        };
+        let mut take_lines = |name: &str| -> syn::Result<Vec<String>> {
+            Ok(options.take_str(name)?
+                .map(|value| value.value().lines().map(|s| s.to_string()).collect())
+                .unwrap_or_default())
+        };
+        let uses = take_lines(USES_OPTION)?;
+        let layers = take_lines(LAYERS_OPTION)?;
+        let tags = take_lines(TAGS_OPTION)?;
+        let prefix = options.take_str(PREFIX_OPTION)?.map(|prefix| prefix.value()).unwrap_or_default();
+        let version = options.take_str(VERSION_OPTION)?.map(|version| version.value()).unwrap_or_default();
+        let state = options.take_str(STATE_OPTION)?.map(|state| state.value()).unwrap_or_default();
+        Ok(Some(UseCollector { mod_name: mod_name.value(), uses, prefix, version, layers, state, tags }))
+    }
+
+    /// the module context of a route: injected by `#[handlers]`, registered for a file module, or empty
+    fn of_route(options: &mut RouteOptions) -> syn::Result<UseCollector> {
+        let use_collector = UseCollector::from_route_options(options)?;
+        #[cfg(feature = "nightly")]
+        let use_collector = use_collector.or_else(nightly::file_module_of_call_site);
+        Ok(use_collector.unwrap_or_else(UseCollector::new))
+    }
+
+    /// the route path under the module prefix, `/v1` goes before the prefix
+    fn full_path(&self, path: &str, version: &str) -> String {
+        let path = route_path::join_prefix(&self.prefix, path);
+        if version.is_empty() {
+            path
+        } else {
+            route_path::join_prefix(&format!("/{}", version), &path)
+        }
+    }
+
+    /// the router state type, `()` unless the module declares one
+    fn state_type(&self) -> syn::Result<syn::Type> {
+        if self.state.is_empty() {
+            Ok(syn::parse_quote! { () })
+        } else {
+            syn::parse_str(&self.state)
+        }
+    }
+
+    /// the module name, without #[handlers] the last segment of the module path at runtime
+    fn mod_name_tokens(&self) -> TokenStream2 {
+        let mod_name = &self.mod_name;
+        if mod_name.is_empty() {
+            quote! { module_path!().rsplit("::").next().unwrap_or_default() }
+        } else {
+            quote! { #mod_name }
+        }
+    }
+
+    /// the layers of the module, outermost first
+    fn layer_tokens(&self) -> syn::Result<Vec<TokenStream2>> {
+        self.layers.iter()
+            .map(|module_layer| layer::parse_layer(module_layer, proc_macro2::Span::call_site()))
+            .collect()
+    }
+
+    /// the module's tags first, then the route's own `tags` option
+    fn tags_with(&self, options: &mut RouteOptions) -> syn::Result<Vec<String>> {
+        let mut tags = self.tags.clone();
+        let route_tags = handlers::parse_tags(options.take_str_list("tags")?)?;
+        tags.extend(route_tags.into_iter().filter(|tag| !self.tags.contains(tag)));
+        Ok(tags)
+    }
+
+    /// append this module context to the arguments of a `#[route(...)]` attribute
+    fn inject_into(&self, attr: &mut Attribute) {
+        // a bare `#[fallback]` gets the arguments to append to
+        if let Meta::Path(path) = &attr.meta {
+            attr.meta = Meta::List(syn::MetaList {
+                path: path.clone(),
+                delimiter: syn::MacroDelimiter::Paren(Default::default()),
+                tokens: TokenStream2::new(),
+            });
+        }
+        if let Meta::List(list) = &mut attr.meta {
+            let mod_name_option = Ident::new(MOD_NAME_OPTION, proc_macro2::Span::call_site());
+            let uses_option = Ident::new(USES_OPTION, proc_macro2::Span::call_site());
+            let prefix_option = Ident::new(PREFIX_OPTION, proc_macro2::Span::call_site());
+            let mod_name = &self.mod_name;
+            let uses = self.uses.join("\n");
+            let prefix = &self.prefix;
+            let version_option = Ident::new(VERSION_OPTION, proc_macro2::Span::call_site());
+            let version = &self.version;
+            let layers_option = Ident::new(LAYERS_OPTION, proc_macro2::Span::call_site());
+            let layers = self.layers.join("\n");
+            let state_option = Ident::new(STATE_OPTION, proc_macro2::Span::call_site());
+            let state = &self.state;
+            let tags_option = Ident::new(TAGS_OPTION, proc_macro2::Span::call_site());
+            let tags = self.tags.join("\n");
+            let tokens = &list.tokens;
+            // don't double the separator when the user left a trailing comma
+            let separator = match tokens.clone().into_iter().last() {
+                None => quote! {},
+                Some(TokenTree::Punct(p)) if p.as_char() == ',' => quote! {},
+                Some(_) => quote! {,},
+            };
+            list.tokens = quote! { #tokens #separator #mod_name_option = #mod_name, #uses_option = #uses, #prefix_option = #prefix, #version_option = #version, #layers_option = #layers, #state_option = #state, #tags_option = #tags };
         }
     }
 }
@@ -60,7 +186,7 @@ fn uses_to_map(use_statements: Vec<String>) -> HashMap<String, String> {
 
     for statement in use_statements {
         // remove the trailing ;
-        let statement = statement.trim_end_matches(';');
+        let statement = statement.trim().trim_end_matches(';');
 
         // make sure starts with `use`
         if !statement.starts_with("use ") {
@@ -97,7 +223,7 @@ fn uses_to_map(use_statements: Vec<String>) -> HashMap<String, String> {
             // get the last segment as key
             let segments: Vec<&str> = full_path.split("::").collect();
             if let Some(last_segment) = segments.last() {
-                let entry_path = format!("{}", full_path.replace(" ",""));
+                let entry_path = full_path.replace(" ","");
                 result_map.insert(last_segment.trim().to_string(), entry_path);
             }
         }
@@ -106,65 +232,95 @@ fn uses_to_map(use_statements: Vec<String>) -> HashMap<String, String> {
     result_map
 }
 
+/// the options `#[route]` knows, besides the ones `#[handlers]` injects
+const ROUTE_OPTIONS: [&str; 15] = ["method", "version", "layer", "name", "tags", "priority", "deprecated", "deprecated_since", "sunset", "kind", "ws_send", "ws_receive", "sse_event", "debug", "non_api"];
+
+/// names of the attributes declaring a route, `#[route]` and its HTTP-method shorthands
+const ROUTE_ATTRS: [&str; 11] = ["route", "get", "post", "put", "delete", "head", "options", "trace", "patch", "fallback", "nest_service"];
+
+/// whether the attribute declares a route, possibly written with a path like `#[axum_route_macro::get(...)]`
+fn is_route_attr(attr: &Attribute) -> bool {
+    attr.path().segments.last().map(|segment| ROUTE_ATTRS.iter().any(|name| segment.ident == name)).unwrap_or(false)
+}
+
 /// A macro to parse the `use` statements of a module.
 /// For this to work, you must define a module with code immediately following.
 /// For example:
 ///
-/// ```rust
+/// ```rust,ignore
+/// #[handlers]
 /// pub mod a_mod {
 ///     // Code for a_mod goes here...
 /// }
 /// ```
+///
+/// The module name and the collected `use` statements are handed to every `#[route]`
+/// directly inside the module by appending them to the route's arguments, so no state
+/// has to be shared between macro invocations.
+/// File modules (`#[handlers] pub mod a_mod;`) are only supported with the `nightly` feature.
+///
+/// Options:
+/// - `prefix = "/api/product"` is prepended to the path of every route in the module
+/// - `version = "v1"` puts every route in the module under `/v1`, before the prefix, and records the version for the api clients
+/// - `layer = "crate::mw::require_login"` or a list of them wraps every route in the module,
+///   a bare path is a middleware function for `axum::middleware::from_fn`, anything else a tower layer expression
+/// - `state = crate::AppState` registers the module's routes for `Router<AppState>`, so handlers can take `State<AppState>`;
+///   the type is resolved inside the module and the routes are added by `add_routes::<AppState>`
+/// - `tags = ["admin"]` tags every route in the module, see `add_routes_tagged`
+///
+/// On an impl block, `#[handlers] impl ProductController { ... }`, the routes of the associated fns
+/// are registered with the `<ProductController>::fn` handler path and the type name in snake case as module name,
+/// e.g. `product_controller`. The same options apply, the `use` statements of the enclosing module aren't collected.
 #[proc_macro_attribute]
-pub fn handlers(_attr: TokenStream, item: TokenStream) -> TokenStream {
-
-    let mut use_collector_map = USE_COLLECTOR_MAP.lock().unwrap();
+pub fn handlers(attr: TokenStream, item: TokenStream) -> TokenStream {
 
-    // parse the mod's content
-    let input = parse_macro_input!(item as ItemMod);
+    let handlers_def = parse_macro_input!(attr as HandlersDef);
 
-    // Get the module file path and name
-    let span = Span::call_site();
-    let source = span.source_file();
-    let file_path = source.path().to_str().unwrap().to_string();
-    let mut map_key = file_path.clone();
-    let mod_name = input.ident.clone().to_string();
+    // an impl block of a controller type, or a module
+    let mut input = match parse_macro_input!(item as Item) {
+        Item::Mod(item_mod) => item_mod,
+        Item::Impl(item_impl) => return controller::expand_impl(handlers_def, item_impl),
+        other => {
+            return syn::Error::new_spanned(other, "#[handlers] expects a module or an impl block").into_compile_error().into();
+        },
+    };
+    let mod_name = input.ident.to_string();
 
     let mut collector = UseCollector {
         mod_name: mod_name.clone(),
         uses: Vec::new(),
+        prefix: handlers_def.prefix,
+        version: handlers_def.version,
+        layers: handlers_def.layers,
+        state: handlers_def.state,
+        tags: handlers_def.tags,
     };
 
     //collect use statements directly written inside pub mod xxx{ use .... }
     collector.visit_item_mod(&input);
 
-    //collect use statements written at a separate file xxx.rs
-    // Construct the path to a.rs based on the current module
-    let extra_file_path = std::path::Path::new(&file_path)
-        .parent()
-        .unwrap()
-        .join(format!("{}.rs",mod_name))
-        .to_str().unwrap().to_string();
-
-    // Try to read the content of a.rs and handle errors
-    match std::fs::read_to_string(&extra_file_path.clone()) {
-        Ok(source) => {
-            map_key = extra_file_path;
-            // Collect use statements
-            for line in source.lines() {
-                let trimmed_line = line.trim();
-                if trimmed_line.starts_with("use ") {
-                    collector.uses.push(trimmed_line.to_string());
+    match &mut input.content {
+        Some((_, items)) => {
+            //hand the module context to the routes of this module
+            for item in items.iter_mut() {
+                if let Item::Fn(item_fn) = item {
+                    for attr in item_fn.attrs.iter_mut().filter(|attr| is_route_attr(attr)) {
+                        collector.inject_into(attr);
+                    }
                 }
             }
         },
-        Err(e) => {
-            //eprintln!("Failed to read file {}: {}", file_path, e);
+        None => {
+            //use statements written at a separate file xxx.rs
+            #[cfg(feature = "nightly")]
+            nightly::register_file_module(collector);
+            #[cfg(not(feature = "nightly"))]
+            return syn::Error::new_spanned(
+                &input,
+                "#[handlers] needs an inline module body; enable the `nightly` feature of axum-route-macro to use it on file modules",
+            ).into_compile_error().into();
         }
-    };
-
-    //Store the collected use statements in the static USE_COLLECTOR_MAP
-    use_collector_map.insert(map_key, collector);
+    }
 
     // return the orginal mod
     let original = quote! {
@@ -173,12 +329,24 @@ pub fn handlers(_attr: TokenStream, item: TokenStream) -> TokenStream {
     original.into()
 }
 
+/// the extractors of the handler arguments a client sends
+const CLIENT_EXTRACTORS: [&str; 4] = ["Path", "Query", "Json", "Form"];
+
+/// whether the last segment of the type's path is `type_name`, generics aside
+fn is_named(ty: &syn::Type, type_name: &str) -> bool {
+    match ty {
+        syn::Type::Path(type_path) => type_path.path.segments.last().is_some_and(|segment| segment.ident == type_name),
+        _ => false,
+    }
+}
+
 fn filter_use_statements(
     collected_uses_map: HashMap<String, String>,
     fn_args: Vec<String>,
     fn_return_type: String,
 ) -> Vec<String> {
-    let mut used_types = HashSet::<String>::new();
+    // sorted so the use statements of a route come out the same on every build
+    let mut used_types = BTreeSet::<String>::new();
 
     // Function to extract types from a given string
     let mut extract_types = |s: &str| {
@@ -207,42 +375,178 @@ fn filter_use_statements(
         .collect()
 }
 
+/// Register a function as a route handler, e.g. `#[route("/api/product/{id}", method = "get")]`.
+///
+/// Option values are strings, booleans, integers, paths, arrays of them, or bare flags:
+/// - `method = "get"`, `method = "get,head"` or `method = ["get", "head"]`
+/// - `version = "v1"` serves the route under `/v1`, replacing the version of its module, see `ApiClientCodeGenConf::with_version`
+/// - `layer = crate::mw::audit`, a layer expression string like `"TimeoutLayer::new(..)"` or a list of them, wrapping only this handler
+/// - `name = "product.detail"` names the route for `axum_route_helper::url_for`
+/// - `tags = ["admin"]` tags the route, in addition to the tags of its module, see `add_routes_tagged`
+/// - `priority = 10` registers the route before the routes of lower priority, `0` by default, so `priority = -1` registers it after them, see `add_routes`
+/// - `deprecated = "use /v2/product"` (or a bare `deprecated`) adds `#[deprecated]` to the generated client function,
+///   and a `Deprecation` header to the responses once the route has a `deprecated_since` or `sunset` date
+/// - `deprecated_since = "2026-06-01"` deprecates the route since that date, sent as `Deprecation: @<unix-seconds>`
+/// - `sunset = "2027-01-01"` adds a `Sunset` header with the date the route goes away
+/// - `kind = "ws"` marks a WebSocket route, detected by itself when the handler takes `WebSocketUpgrade`;
+///   `ws_send = ClientMessage` and `ws_receive = ServerMessage` type the messages of the generated client, `String` by default
+/// - `kind = "sse"` marks a Server-Sent Events route, detected by itself when the handler returns `Sse<...>`;
+///   `sse_event = Tick` types the events of the generated client, `String` by default
+/// - `debug` writes what the route expanded to a JSON file under the target directory,
+///   for every route when the `AXUM_ROUTE_DEBUG=1` env var is set at build time
+/// - `non_api` (or `non_api = true`) keeps the function as is, without registering a route
 #[proc_macro_attribute]
 pub fn route(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let ic = item.clone();
-    let input_fn = parse_macro_input!(ic as ItemFn);
-    let fn_name = &input_fn.sig.ident;
-    // Emit a warning during compilation
-    let func_span = fn_name.span(); // Get the span of the function name
+    expand_route(attr, item, None)
+}
+
+/// Shorthand for `#[route("/path", method = "get")]`, taking the same options as `#[route]`.
+#[proc_macro_attribute]
+pub fn get(attr: TokenStream, item: TokenStream) -> TokenStream {
+    expand_route(attr, item, Some("get"))
+}
+
+/// Shorthand for `#[route("/path", method = "post")]`, taking the same options as `#[route]`.
+#[proc_macro_attribute]
+pub fn post(attr: TokenStream, item: TokenStream) -> TokenStream {
+    expand_route(attr, item, Some("post"))
+}
 
-    let mut msgs:Vec<String> = vec![];
-    msgs.push("==================================".to_string());
-    msgs.push(format!("fn name {}",fn_name));
+/// Shorthand for `#[route("/path", method = "put")]`, taking the same options as `#[route]`.
+#[proc_macro_attribute]
+pub fn put(attr: TokenStream, item: TokenStream) -> TokenStream {
+    expand_route(attr, item, Some("put"))
+}
+
+/// Shorthand for `#[route("/path", method = "delete")]`, taking the same options as `#[route]`.
+#[proc_macro_attribute]
+pub fn delete(attr: TokenStream, item: TokenStream) -> TokenStream {
+    expand_route(attr, item, Some("delete"))
+}
+
+/// Shorthand for `#[route("/path", method = "head")]`, taking the same options as `#[route]`.
+#[proc_macro_attribute]
+pub fn head(attr: TokenStream, item: TokenStream) -> TokenStream {
+    expand_route(attr, item, Some("head"))
+}
 
-    let span = Span::call_site();
-    let source = span.source_file();
-    let file_path = source.path().to_str().unwrap().to_string();
-    msgs.push(format!("file path {}",source.path().to_str().unwrap()));
+/// Shorthand for `#[route("/path", method = "options")]`, taking the same options as `#[route]`.
+#[proc_macro_attribute]
+pub fn options(attr: TokenStream, item: TokenStream) -> TokenStream {
+    expand_route(attr, item, Some("options"))
+}
+
+/// Shorthand for `#[route("/path", method = "trace")]`, taking the same options as `#[route]`.
+#[proc_macro_attribute]
+pub fn trace(attr: TokenStream, item: TokenStream) -> TokenStream {
+    expand_route(attr, item, Some("trace"))
+}
+
+/// Shorthand for `#[route("/path", method = "patch")]`, taking the same options as `#[route]`.
+#[proc_macro_attribute]
+pub fn patch(attr: TokenStream, item: TokenStream) -> TokenStream {
+    expand_route(attr, item, Some("patch"))
+}
+
+/// Register a handler function as the router's fallback, serving the requests no route matches, e.g. `#[fallback]`.
+///
+/// Options: `layer` and `tags` like `#[route]`; the module's layers, tags and state apply as well.
+#[proc_macro_attribute]
+pub fn fallback(attr: TokenStream, item: TokenStream) -> TokenStream {
+    mount::expand_fallback(attr, item)
+}
+
+/// Nest the tower service returned by a function under a path, e.g.
+/// `#[nest_service("/assets")] fn assets() -> ServeDir { ServeDir::new("assets") }`.
+///
+/// The path gets the module's prefix and version. Options: `layer` and `tags` like `#[route]`.
+#[proc_macro_attribute]
+pub fn nest_service(attr: TokenStream, item: TokenStream) -> TokenStream {
+    mount::expand_nest_service(attr, item)
+}
+
+/// expand a route attribute, `implied_method` is given by the HTTP-method shorthands
+fn expand_route(attr: TokenStream, item: TokenStream, implied_method: Option<&str>) -> TokenStream {
+    let input_fn = parse_macro_input!(item as ItemFn);
+    expand_route_fn(attr, input_fn, implied_method, None)
+}
+
+/// expand the route of a handler fn, or of an associated fn of `self_ty` given by `#[handlers] impl`,
+/// which stays in the impl block while the route provider goes next to it
+fn expand_route_fn(attr: TokenStream, input_fn: ItemFn, implied_method: Option<&str>, self_ty: Option<&syn::Type>) -> TokenStream {
+    let fn_name = &input_fn.sig.ident;
+    let original_fn = match self_ty {
+        Some(_) => quote! {},
+        None => quote! { #input_fn },
+    };
 
     //extract the route's method and path
-    let routeDef:RouteDef = match syn::parse(attr) {
+    let mut route_def:RouteDef = match syn::parse(attr) {
         Ok(args) => args,
         Err(err) => return err.into_compile_error().into(),
     };
 
-    msgs.push(format!("attr parsed routeDef {:#?}",routeDef));
+    // a non_api function is kept as is, without a route
+    match route_def.options.take_flag("non_api") {
+        Ok(true) => return TokenStream::from(original_fn),
+        Ok(false) => {},
+        Err(err) => return err.into_compile_error().into(),
+    }
+
+    // dump the expansion to a JSON file, see the debug module
+    let debug_option = match route_def.options.take_flag("debug") {
+        Ok(debug_option) => debug_option,
+        Err(err) => return err.into_compile_error().into(),
+    };
+
+    if let Some(method) = implied_method {
+        if !route_def.methods.is_empty() {
+            return syn::Error::new(
+                proc_macro2::Span::call_site(),
+                format!("the method is already given by #[{}], remove the `method` option", method),
+            ).into_compile_error().into();
+        }
+        route_def.methods = vec![method.to_string()];
+    }
 
-    // gain a USE_COLLECTOR_MAP lock
-    let collector_map = USE_COLLECTOR_MAP.lock().unwrap();
+    if route_def.methods.is_empty() {
+        return syn::Error::new(
+            proc_macro2::Span::call_site(),
+            format!(
+                r#"Missing route method, expected #[route("{}", method = "get")], possible methods are {}"#,
+                route_def.path,
+                route::POSSIBLE_METHODS.join(", "),
+            ),
+        ).into_compile_error().into();
+    }
 
-    let mut use_collector = UseCollector::new();
+    // the module context is injected by #[handlers], a route outside of it gets its module name at runtime
+    let use_collector = match UseCollector::of_route(&mut route_def.options) {
+        Ok(use_collector) => use_collector,
+        Err(err) => return err.into_compile_error().into(),
+    };
+    // the route's own version replaces the one of its module, `/v1` goes before the module prefix
+    let version = match route_def.options.take_str("version") {
+        Ok(Some(version)) => match route_path::parse_version(&version) {
+            Ok(version) => version,
+            Err(err) => return err.into_compile_error().into(),
+        },
+        Ok(None) => use_collector.version.clone(),
+        Err(err) => return err.into_compile_error().into(),
+    };
+    route_def.path = use_collector.full_path(&route_def.path, &version);
 
-    if let Some(uc) = collector_map.get(&file_path) {
-        use_collector = uc.clone();
+    // the path placeholders have to match the handler's Path extractor
+    let placeholders = match route_path::parse_placeholders(&route_def.path, route_def.path_span) {
+        Ok(placeholders) => placeholders,
+        Err(err) => return err.into_compile_error().into(),
+    };
+    if let Err(err) = route_path::check_path_extractor(&placeholders, &input_fn) {
+        return err.into_compile_error().into();
     }
 
-    // from USE_COLLECTOR clone the `uses` statements and convert into a map
-    let collected_uses_map = uses_to_map(use_collector.uses);
+    // from the module's `uses` statements build a map
+    let collected_uses_map = uses_to_map(use_collector.uses.clone());
 
     // Collect argument types and names
     let fn_args: Vec<String> = input_fn.sig.inputs.iter().filter_map(|arg| {
@@ -260,59 +564,190 @@ pub fn route(attr: TokenStream, item: TokenStream) -> TokenStream {
         ReturnType::Default => "()".to_string(),
     };
 
-    let use_statements = filter_use_statements(collected_uses_map.clone(),fn_args.clone(),fn_return_type.clone());
-
-    // Prepare message output
-    msgs.push(format!("fn args {:#?}, return type {:#?}, use_statements {:#?}, collector_map {:#?}", file_path, fn_args, fn_return_type, use_statements));
-
-    /*let ast = match syn::parse::<syn::ItemFn>(item.clone()) {
-        Ok(ast) => ast,
+    // a WebSocket route, or a plain one
+    let handler_kind = match kind::HandlerKind::take_from(&mut route_def.options, &input_fn) {
+        Ok(handler_kind) => handler_kind,
         Err(err) => return err.into_compile_error().into(),
-    };*/
+    };
+    let with_kind = handler_kind.desc_setters();
+    let with_self_type = self_ty.map(|self_ty| {
+        let self_type = self_ty.to_token_stream().to_string().replace(' ', "");
+        quote! { .with_self_type(#self_type.to_string()) }
+    });
+
+    // clients only send the extracted path, query and body, not e.g. `State(state): State<AppState>` or a `HeaderMap`, nor import their types
+    let client_args: Vec<String> = input_fn.sig.inputs.iter().filter_map(|arg| match arg {
+        FnArg::Typed(pat_type) if CLIENT_EXTRACTORS.iter().any(|extractor| is_named(&pat_type.ty, extractor)) => Some(quote! { #pat_type }.to_string().replace(" ", "")),
+        _ => None,
+    }).collect();
+    // the client of an upgrade or event stream doesn't see the handler's response type
+    let used_types = [client_args, handler_kind.used_types()].concat();
+    let used_return_type = if handler_kind.is_http() { fn_return_type.clone() } else { String::new() };
+    let use_statements = filter_use_statements(collected_uses_map.clone(),used_types,used_return_type);
 
-    let mod_name = use_collector.mod_name.clone();
-    let path = routeDef.path;
-    let httpd_method = routeDef.method;
+    let path = route_def.path;
+    let httpd_methods = route_def.methods;
     let fn_name = fn_name.to_string();
     let fn_args = fn_args.join(";");
     let use_statements = use_statements.join(";");
-    let dynamic_struct_name = Ident::new(&format!("RouteProvider{}",&fn_name), proc_macro2::Span::call_site());
-    let method_ident = Ident::new(&format!("{}",&httpd_method), proc_macro2::Span::call_site());
-    let handler_ident = Ident::new(&format!("{}",&fn_name), proc_macro2::Span::call_site());
+    let handler_ident = Ident::new(&fn_name, proc_macro2::Span::call_site());
+
+    // stable name of the route for url_for
+    let route_name = match route_def.options.take_str("name") {
+        Ok(Some(name)) if name.value().trim().is_empty() => {
+            return syn::Error::new(name.span(), "The route name can't be empty.").into_compile_error().into();
+        },
+        Ok(name) => name.map(|name| name.value()),
+        Err(err) => return err.into_compile_error().into(),
+    };
+    let with_name = route_name.map(|name| quote! { .with_name(#name.to_string()) });
+    let with_version = (!version.is_empty()).then(|| quote! { .with_version(#version.to_string()) });
 
-    let is_to_expand = !routeDef.options.contains_key("non_api");
+    // routes of higher priority are registered first
+    let with_priority = match route_def.options.take_int::<i32>("priority") {
+        Ok(priority) => priority.map(|priority| quote! { .with_priority(#priority) }),
+        Err(err) => return err.into_compile_error().into(),
+    };
 
-    let original = quote! {
-        #input_fn // Keep the original function
+    let deprecation = match deprecation::Deprecation::take_from(&mut route_def.options) {
+        Ok(deprecation) => deprecation,
+        Err(err) => return err.into_compile_error().into(),
     };
+    let with_deprecated = deprecation.note.as_ref().map(|note| quote! { .with_deprecated(#note.to_string()) });
+    let with_sunset = deprecation.sunset.as_ref().map(|sunset| quote! { .with_sunset(#sunset.to_string()) });
 
-    // Generate the FnInfo struct
-    let expanded = quote! {
-        #input_fn // Keep the original function
+    let tags = match use_collector.tags_with(&mut route_def.options) {
+        Ok(tags) => tags,
+        Err(err) => return err.into_compile_error().into(),
+    };
 
-        #[allow(non_camel_case_types, missing_docs)]
-        pub struct #dynamic_struct_name;
+    if let Err(err) = route_def.options.ensure_empty(&ROUTE_OPTIONS) {
+        return err.into_compile_error().into();
+    }
 
-        // Implement RouteProvider for struct #name
-        impl axum_route_helper::RouteProvider for #dynamic_struct_name {
-            fn add_route(&self, router: axum::Router) -> axum::Router {
-                router.route(#path,axum::routing::#method_ident(#handler_ident))
-            }
-            fn get_route(&self) -> axum_route_helper::RouteMethodDesc {
-                axum_route_helper::RouteMethodDesc::new(#mod_name.to_string(),#path.to_string(),#httpd_method.to_string(),#fn_name.to_string(),#fn_args.to_string(),#fn_return_type.to_string(),#use_statements.to_string())
-            }
+    let state = match use_collector.state_type() {
+        Ok(state) => state,
+        Err(err) => return err.into_compile_error().into(),
+    };
+    let mod_name = use_collector.mod_name_tokens();
+
+    // chain the handler onto every method, e.g. axum::routing::get(handler).head(handler)
+    let method_idents: Vec<Ident> = httpd_methods.iter().map(|method| Ident::new(method, proc_macro2::Span::call_site())).collect();
+    let first_method_ident = &method_idents[0];
+    let other_method_idents = &method_idents[1..];
+    let httpd_method = &httpd_methods[0];
+    let handler = match self_ty {
+        Some(self_ty) => quote! { <#self_ty>::#handler_ident },
+        None => quote! { #handler_ident },
+    };
+    let method_router = quote! { axum::routing::#first_method_ident(#handler) #(.#other_method_idents(#handler))* };
+
+    // wrap the handler in its own layers, then in the layers of its module
+    let mut layers = vec![];
+    for route_layer in route_def.layers.iter() {
+        match layer::parse_layer(&route_layer.value(), route_layer.span()) {
+            Ok(tokens) => layers.push(tokens),
+            Err(err) => return err.into_compile_error().into(),
         }
+    }
+    let method_router = layer::apply_layers(method_router, &layers);
+    let mut layers = match use_collector.layer_tokens() {
+        Ok(layers) => layers,
+        Err(err) => return err.into_compile_error().into(),
+    };
+    // the deprecation headers go outermost, so responses rejected by a layer carry them as well
+    layers.splice(0..0, deprecation.layer());
+    let method_router = layer::apply_layers(method_router, &layers);
+
+    let register = quote! { router.route(#path,#method_router) };
+    let desc = quote! {
+        axum_route_helper::RouteMethodDesc::new(#mod_name.to_string(),#path.to_string(),#httpd_method.to_string(),#fn_name.to_string(),#fn_args.to_string(),#fn_return_type.to_string(),#use_statements.to_string())
+            .with_http_methods(vec![#(#httpd_methods.to_string()),*])
+            #with_name
+            #with_priority
+            #with_version
+            #with_deprecated
+            #with_sunset
+            #with_kind
+            #with_self_type
+            .with_tags(vec![#(#tags.to_string()),*])
+    };
+    // associated fns of different types may share a name
+    let provider_name = match self_ty {
+        Some(self_ty) => format!("{}_{}", controller::type_name(self_ty), fn_name),
+        None => fn_name.clone(),
+    };
+    let route_provider = route_provider(&provider_name, input_fn.sig.ident.span(), &state, register, desc);
 
-        axum_route_helper::register_route_provider!(#dynamic_struct_name);
+    // Generate the FnInfo struct
+    let expanded = quote! {
+        #original_fn // Keep the original function
+
+        #route_provider
     };
 
-    msgs.push("==================================".to_string());
-    // 创建一个help
-    let mut diagnostic = Diagnostic::new(Level::Help, msgs.join("\n"));
-    diagnostic.set_spans(vec![Span::def_site()]);
-    diagnostic.emit(); // 发出Help
+    if debug::enabled(debug_option) {
+        let route_layers: Vec<String> = route_def.layers.iter().map(|layer| layer.value()).collect();
+        let record = debug::Record::default()
+            .str("crate", &std::env::var("CARGO_PKG_NAME").unwrap_or_default())
+            .str("mod_name", &use_collector.mod_name)
+            .str("self_type", &self_ty.map(|self_ty| self_ty.to_token_stream().to_string()).unwrap_or_default())
+            .str("fn_name", &fn_name)
+            .str("path", &path)
+            .list("methods", &httpd_methods)
+            .str("kind", &format!("{:?}", handler_kind))
+            .str("state", &use_collector.state)
+            .str("version", &version)
+            .list("module_layers", &use_collector.layers)
+            .list("route_layers", &route_layers)
+            .list("tags", &tags)
+            .str("fn_args", &fn_args)
+            .str("fn_return_type", &fn_return_type)
+            .str("use_statements", &use_statements)
+            .list("module_uses", &use_collector.uses);
+        if let Err(err) = record.write(&fn_name, &format!("{} {} {}", provider_name, path, httpd_methods.join(","))) {
+            return err.into_compile_error().into();
+        }
+    }
 
-    if is_to_expand {TokenStream::from(expanded)} else {TokenStream::from(original)}
+    TokenStream::from(expanded)
 }
 
+/// The `RouteProvider` of a handler fn and its registration: `register` adds the handler to `router`,
+/// a `Router` of the `state` type, `desc` is the `RouteMethodDesc` of `get_route`, located at `location`.
+/// The provider type is hidden in an anonymous const, named after the handler and a hash of the route
+/// and the call site, so same-named handlers of different modules don't clash
+fn route_provider(fn_name: &str, location: proc_macro2::Span, state: &syn::Type, register: TokenStream2, desc: TokenStream2) -> TokenStream2 {
+    let mut hasher = DefaultHasher::new();
+    fn_name.hash(&mut hasher);
+    register.to_string().hash(&mut hasher);
+    desc.to_string().hash(&mut hasher);
+    format!("{:?}", proc_macro2::Span::call_site()).hash(&mut hasher);
+    let dynamic_struct_name = Ident::new(&format!("__RouteProvider_{}_{:016x}", fn_name, hasher.finish()), proc_macro2::Span::call_site());
+    // file!() and line!() spanned at the fn name tell where the handler is declared
+    let source_location = quote_spanned! { location => .with_source_location(file!().to_string(), line!()) };
+    quote! {
+        const _: () = {
+            #[doc(hidden)]
+            #[allow(non_camel_case_types)]
+            struct #dynamic_struct_name;
+
+            // Implement RouteProvider for struct #name
+            impl axum_route_helper::RouteProvider for #dynamic_struct_name {
+                fn state_type(&self) -> std::any::TypeId {
+                    std::any::TypeId::of::<#state>()
+                }
+                fn add_route(&self, router: axum_route_helper::AnyRouter) -> axum_route_helper::AnyRouter {
+                    router.map(|router: axum::Router<#state>| #register)
+                }
+                fn get_route(&self) -> axum_route_helper::RouteMethodDesc {
+                    #desc
+                        .with_module_path(module_path!().to_string())
+                        #source_location
+                }
+            }
 
+            axum_route_helper::register_route_provider!(#dynamic_struct_name);
+        };
+    }
+}
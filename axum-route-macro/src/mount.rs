@@ -0,0 +1,148 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, ItemFn};
+use crate::options::RouteOptions;
+use crate::route::RouteDef;
+use crate::{layer, route_path, route_provider, UseCollector};
+
+/// the options `#[fallback]` and `#[nest_service]` know
+const MOUNT_OPTIONS: [&str; 2] = ["layer", "tags"];
+
+/// Register the handler fn as the fallback of the router, see `crate::fallback`
+pub fn expand_fallback(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let ic = item.clone();
+    let input_fn = parse_macro_input!(ic as ItemFn);
+    let fn_name = input_fn.sig.ident.to_string();
+    let handler_ident = &input_fn.sig.ident;
+
+    let mut options: RouteOptions = parse_macro_input!(attr as RouteOptions);
+    let use_collector = match UseCollector::of_route(&mut options) {
+        Ok(use_collector) => use_collector,
+        Err(err) => return err.into_compile_error().into(),
+    };
+    let (layers, tags) = match mount_options(&mut options, &use_collector) {
+        Ok(layers_and_tags) => layers_and_tags,
+        Err(err) => return err.into_compile_error().into(),
+    };
+    let state = match use_collector.state_type() {
+        Ok(state) => state,
+        Err(err) => return err.into_compile_error().into(),
+    };
+    let mod_name = use_collector.mod_name_tokens();
+
+    // a layered handler is still a handler, e.g. Handler::layer(not_found, from_fn(audit))
+    let handler = layers.iter().rev().fold(quote! { #handler_ident }, |handler, layer| {
+        quote! { axum::handler::Handler::layer(#handler, #layer) }
+    });
+    let register = quote! { router.fallback(#handler) };
+    let desc = quote! {
+        axum_route_helper::RouteMethodDesc::new(#mod_name.to_string(),"".to_string(),"any".to_string(),#fn_name.to_string(),"".to_string(),"".to_string(),"".to_string())
+            .with_kind(axum_route_helper::RouteKind::Fallback)
+            .with_tags(vec![#(#tags.to_string()),*])
+    };
+    let route_provider = route_provider(&fn_name, handler_ident.span(), &state, register, desc);
+
+    TokenStream::from(quote! {
+        #input_fn
+
+        #route_provider
+    })
+}
+
+/// Nest the tower service returned by the fn under the path, see `crate::nest_service`
+pub fn expand_nest_service(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let ic = item.clone();
+    let input_fn = parse_macro_input!(ic as ItemFn);
+    let fn_name = input_fn.sig.ident.to_string();
+    let service_ident = &input_fn.sig.ident;
+
+    if !input_fn.sig.inputs.is_empty() || input_fn.sig.asyncness.is_some() {
+        return syn::Error::new_spanned(
+            &input_fn.sig,
+            "#[nest_service] expects a plain fn without arguments returning the service, like `fn assets() -> ServeDir`",
+        ).into_compile_error().into();
+    }
+
+    let mut route_def: RouteDef = match syn::parse(attr) {
+        Ok(route_def) => route_def,
+        Err(err) => return err.into_compile_error().into(),
+    };
+    if !route_def.methods.is_empty() {
+        return syn::Error::new(route_def.path_span, "A nested service takes every method, remove the `method` option.")
+            .into_compile_error().into();
+    }
+    let use_collector = match UseCollector::of_route(&mut route_def.options) {
+        Ok(use_collector) => use_collector,
+        Err(err) => return err.into_compile_error().into(),
+    };
+    let path = use_collector.full_path(&route_def.path, &use_collector.version);
+    if let Err(err) = check_nest_path(&path, route_def.path_span) {
+        return err.into_compile_error().into();
+    }
+
+    let mut own_layers = vec![];
+    for service_layer in route_def.layers.iter() {
+        match layer::parse_layer(&service_layer.value(), service_layer.span()) {
+            Ok(tokens) => own_layers.push(tokens),
+            Err(err) => return err.into_compile_error().into(),
+        }
+    }
+    let (mut layers, tags) = match mount_options(&mut route_def.options, &use_collector) {
+        Ok(layers_and_tags) => layers_and_tags,
+        Err(err) => return err.into_compile_error().into(),
+    };
+    layers.extend(own_layers);
+    let state = match use_collector.state_type() {
+        Ok(state) => state,
+        Err(err) => return err.into_compile_error().into(),
+    };
+    let mod_name = use_collector.mod_name_tokens();
+    let service_type = match &input_fn.sig.output {
+        syn::ReturnType::Type(_, ty) => quote! { #ty }.to_string().replace(' ', ""),
+        syn::ReturnType::Default => "()".to_string(),
+    };
+
+    // the layers wrap the service like those of a route, through a MethodRouter taking any method
+    let service = if layers.is_empty() {
+        quote! { #service_ident() }
+    } else {
+        layer::apply_layers(quote! { axum::routing::any_service(#service_ident()) }, &layers)
+    };
+    let register = quote! { router.nest_service(#path, #service) };
+    let desc = quote! {
+        axum_route_helper::RouteMethodDesc::new(#mod_name.to_string(),#path.to_string(),"any".to_string(),#fn_name.to_string(),"".to_string(),#service_type.to_string(),"".to_string())
+            .with_kind(axum_route_helper::RouteKind::Service)
+            .with_tags(vec![#(#tags.to_string()),*])
+    };
+    let route_provider = route_provider(&fn_name, service_ident.span(), &state, register, desc);
+
+    TokenStream::from(quote! {
+        #input_fn
+
+        #route_provider
+    })
+}
+
+/// the layers, the module's outermost, and the tags of a fallback or nested service
+fn mount_options(options: &mut RouteOptions, use_collector: &UseCollector) -> syn::Result<(Vec<TokenStream2>, Vec<String>)> {
+    let mut layers = use_collector.layer_tokens()?;
+    for own_layer in options.take_str_list("layer")? {
+        layers.push(layer::parse_layer(&own_layer.value(), own_layer.span())?);
+    }
+    let tags = use_collector.tags_with(options)?;
+    options.ensure_empty(&MOUNT_OPTIONS)?;
+    Ok((layers, tags))
+}
+
+/// axum can't nest at the root nor under a wildcard
+fn check_nest_path(path: &str, span: proc_macro2::Span) -> syn::Result<()> {
+    route_path::parse_placeholders(path, span)?;
+    if path.trim_end_matches('/').is_empty() {
+        return Err(syn::Error::new(span, "A service can't be nested at the root, use #[fallback] for a handler serving every other path."));
+    }
+    if path.contains("{*") {
+        return Err(syn::Error::new(span, format!("A service can't be nested under the wildcard of `{}`.", path)));
+    }
+    Ok(())
+}
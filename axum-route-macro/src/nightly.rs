@@ -0,0 +1,50 @@
+//! Extras relying on unstable compiler behaviour, enabled by the `nightly` feature.
+//!
+//! `#[handlers]` on a file module (`#[handlers] pub mod product;`) needs `proc_macro_hygiene`
+//! in the user's crate and can't see the module's items, so the module context is kept in a
+//! static map keyed by the module's source file and looked up again by each `#[route]`.
+//! This relies on all macro invocations of a crate sharing one process, which rustc doesn't promise.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use once_cell::sync::Lazy;
+use proc_macro::Span;
+use crate::UseCollector;
+
+static USE_COLLECTOR_MAP: Lazy<Mutex<HashMap<PathBuf,UseCollector>>> = Lazy::new(|| {
+    Mutex::new(HashMap::new())
+});
+
+/// remember the module context of a file module, collecting the `use` statements of `<mod_name>.rs`
+pub(crate) fn register_file_module(mut collector: UseCollector) {
+    let Some(file_path) = Span::call_site().local_file() else {
+        return;
+    };
+    let Some(dir) = file_path.parent() else {
+        return;
+    };
+
+    let candidates = [
+        dir.join(format!("{}.rs", collector.mod_name)),
+        dir.join(&collector.mod_name).join("mod.rs"),
+    ];
+    for mod_file_path in candidates {
+        if let Ok(source) = std::fs::read_to_string(&mod_file_path) {
+            for line in source.lines() {
+                let trimmed_line = line.trim();
+                if trimmed_line.starts_with("use ") {
+                    collector.uses.push(trimmed_line.to_string());
+                }
+            }
+            USE_COLLECTOR_MAP.lock().unwrap().insert(mod_file_path, collector);
+            return;
+        }
+    }
+}
+
+/// the module context registered for the file the current `#[route]` is written in
+pub(crate) fn file_module_of_call_site() -> Option<UseCollector> {
+    let file_path = Span::call_site().local_file()?;
+    USE_COLLECTOR_MAP.lock().unwrap().get(&file_path).cloned()
+}
@@ -0,0 +1,265 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::ToTokens;
+use syn::{Ident, LitStr, Token};
+use syn::ext::IdentExt;
+use syn::spanned::Spanned;
+
+/// Value of an option of `#[route]` or `#[handlers]`
+#[derive(Debug, Clone)]
+pub enum OptionValue {
+    /// `name = "text"`
+    Str(LitStr),
+    /// `name = true`
+    Bool(syn::LitBool),
+    /// `name = 30` or `name = -1`
+    Int(syn::LitInt),
+    /// `name = crate::mw::audit`
+    Path(syn::Path),
+    /// `name = ["a", "b"]`
+    Array(Vec<OptionValue>),
+    /// a bare `name`
+    Flag,
+}
+
+impl OptionValue {
+    fn from_expr(expr: &syn::Expr) -> syn::Result<OptionValue> {
+        match expr {
+            syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(lit_str), .. }) => Ok(OptionValue::Str(lit_str.clone())),
+            syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Bool(lit_bool), .. }) => Ok(OptionValue::Bool(lit_bool.clone())),
+            syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Int(lit_int), .. }) => Ok(OptionValue::Int(lit_int.clone())),
+            // a negative integer is a negation of its literal, e.g. `priority = -1`
+            syn::Expr::Unary(syn::ExprUnary { op: syn::UnOp::Neg(_), expr: operand, .. }) => match operand.as_ref() {
+                syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Int(lit_int), .. }) => {
+                    Ok(OptionValue::Int(syn::LitInt::new(&format!("-{}", lit_int), expr.span())))
+                },
+                _ => Err(syn::Error::new_spanned(expr, "Expected an integer after `-` for the option value.")),
+            },
+            syn::Expr::Path(expr_path) if expr_path.qself.is_none() => Ok(OptionValue::Path(expr_path.path.clone())),
+            syn::Expr::Array(array) => Ok(OptionValue::Array(array.elems.iter().map(OptionValue::from_expr).collect::<syn::Result<_>>()?)),
+            _ => Err(syn::Error::new_spanned(expr, "Expected a string, a boolean, an integer, a path or an array for the option value.")),
+        }
+    }
+}
+
+/// An option like `method = "get"`, `priority = 10`, `tags = ["admin"]` or the flag `non_api`
+#[derive(Debug, Clone)]
+pub struct RouteOption {
+    pub name: Ident,
+    pub value: OptionValue,
+    /// the whole option, for errors pointing at `name = value`
+    tokens: TokenStream2,
+}
+
+impl RouteOption {
+    pub fn new(name: Ident, value: OptionValue, tokens: TokenStream2) -> Self {
+        RouteOption { name, value, tokens }
+    }
+
+    fn error(&self, message: &str) -> syn::Error {
+        syn::Error::new_spanned(self, format!("Option `{}`: {}", self.name, message))
+    }
+
+    /// the value as a string literal
+    pub fn as_str(&self) -> syn::Result<LitStr> {
+        match &self.value {
+            OptionValue::Str(lit_str) => Ok(lit_str.clone()),
+            _ => Err(self.error("expected a string literal.")),
+        }
+    }
+
+    /// the value as strings, taking a string, a path or an array of them, e.g. `layer = [crate::mw::audit, "log()"]`
+    pub fn as_str_list(&self) -> syn::Result<Vec<LitStr>> {
+        fn collect(value: &OptionValue, strings: &mut Vec<LitStr>) -> bool {
+            match value {
+                OptionValue::Str(lit_str) => strings.push(lit_str.clone()),
+                OptionValue::Path(path) => strings.push(LitStr::new(&path.to_token_stream().to_string().replace(' ', ""), path.span())),
+                OptionValue::Array(values) => return values.iter().all(|value| collect(value, strings)),
+                _ => return false,
+            }
+            true
+        }
+        let mut strings = vec![];
+        if collect(&self.value, &mut strings) {
+            Ok(strings)
+        } else {
+            Err(self.error("expected a string, a path or an array of them."))
+        }
+    }
+
+    /// the value as a boolean, a bare flag is `true`, `"true"` and `"false"` are accepted as well
+    pub fn as_bool(&self) -> syn::Result<bool> {
+        match &self.value {
+            OptionValue::Flag => Ok(true),
+            OptionValue::Bool(lit_bool) => Ok(lit_bool.value),
+            OptionValue::Str(lit_str) if lit_str.value() == "true" => Ok(true),
+            OptionValue::Str(lit_str) if lit_str.value() == "false" => Ok(false),
+            _ => Err(self.error("expected a boolean or a bare flag.")),
+        }
+    }
+
+    /// the value as an integer of type `N`
+    pub fn as_int<N>(&self) -> syn::Result<N>
+    where
+        N: std::str::FromStr,
+        N::Err: std::fmt::Display,
+    {
+        match &self.value {
+            OptionValue::Int(lit_int) => lit_int.base10_parse(),
+            _ => Err(self.error("expected an integer.")),
+        }
+    }
+
+    /// the value as a type, given as a path or a string like `"Arc<AppState>"`
+    pub fn as_type(&self) -> syn::Result<syn::Type> {
+        match &self.value {
+            OptionValue::Path(path) => syn::parse2(path.to_token_stream()),
+            OptionValue::Str(lit_str) => lit_str.parse(),
+            _ => Err(self.error("expected a type.")),
+        }
+    }
+}
+
+impl ToTokens for RouteOption {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        tokens.extend(self.tokens.clone());
+    }
+}
+
+impl syn::parse::Parse for RouteOption {
+    fn parse(input: syn::parse::ParseStream<'_>) -> syn::Result<Self> {
+        let name = Ident::parse_any(input)?;
+        if input.peek(Token![=]) {
+            let eq: Token![=] = input.parse()?;
+            let expr: syn::Expr = input.parse()?;
+            let value = OptionValue::from_expr(&expr)?;
+            let mut tokens = name.to_token_stream();
+            eq.to_tokens(&mut tokens);
+            expr.to_tokens(&mut tokens);
+            Ok(RouteOption::new(name, value, tokens))
+        } else {
+            let tokens = name.to_token_stream();
+            Ok(RouteOption::new(name, OptionValue::Flag, tokens))
+        }
+    }
+}
+
+/// The options of a `#[route]` or `#[handlers]`, taken out one by one by the code knowing them,
+/// whatever is left at the end is unknown
+#[derive(Debug, Clone, Default)]
+pub struct RouteOptions {
+    options: Vec<RouteOption>,
+}
+
+impl RouteOptions {
+    /// remove the option named `name`
+    pub fn take(&mut self, name: &str) -> Option<RouteOption> {
+        let index = self.options.iter().position(|option| option.name == name)?;
+        Some(self.options.remove(index))
+    }
+
+    pub fn take_str(&mut self, name: &str) -> syn::Result<Option<LitStr>> {
+        self.take(name).map(|option| option.as_str()).transpose()
+    }
+
+    pub fn take_str_list(&mut self, name: &str) -> syn::Result<Vec<LitStr>> {
+        Ok(self.take(name).map(|option| option.as_str_list()).transpose()?.unwrap_or_default())
+    }
+
+    /// a flag is off when missing
+    pub fn take_flag(&mut self, name: &str) -> syn::Result<bool> {
+        Ok(self.take(name).map(|option| option.as_bool()).transpose()?.unwrap_or(false))
+    }
+
+    pub fn take_int<N>(&mut self, name: &str) -> syn::Result<Option<N>>
+    where
+        N: std::str::FromStr,
+        N::Err: std::fmt::Display,
+    {
+        self.take(name).map(|option| option.as_int()).transpose()
+    }
+
+    pub fn take_type(&mut self, name: &str) -> syn::Result<Option<syn::Type>> {
+        self.take(name).map(|option| option.as_type()).transpose()
+    }
+
+    /// fail on the first option nobody took, `known` lists the options for the message
+    pub fn ensure_empty(&self, known: &[&str]) -> syn::Result<()> {
+        match self.options.first() {
+            Some(option) => {
+                let name = option.name.to_string();
+                let closest = known.iter().min_by_key(|known| crate::route::edit_distance(&name, known));
+                Err(syn::Error::new_spanned(
+                    &option.name,
+                    match closest {
+                        Some(closest) => format!("Unknown option `{}`, did you mean `{}`? Expected one of {}.", name, closest, known.join(", ")),
+                        None => format!("Unknown option `{}`.", name),
+                    },
+                ))
+            },
+            None => Ok(()),
+        }
+    }
+}
+
+impl syn::parse::Parse for RouteOptions {
+    fn parse(input: syn::parse::ParseStream<'_>) -> syn::Result<Self> {
+        let mut options: Vec<RouteOption> = vec![];
+        while !input.is_empty() {
+            let option: RouteOption = input.parse()?;
+            if options.iter().any(|existing| existing.name == option.name) {
+                return Err(syn::Error::new_spanned(&option.name, format!("Option `{}` is given more than once.", option.name)));
+            }
+            options.push(option);
+
+            // Check for a comma to continue parsing more options
+            if input.peek(Token![,]) {
+                input.parse::<Token![,]>()?;
+            } else {
+                break;
+            }
+        }
+        Ok(RouteOptions { options })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(tokens: TokenStream2) -> RouteOptions {
+        syn::parse2(tokens).expect("options parse")
+    }
+
+    #[test]
+    fn takes_integers() {
+        let mut options = options(quote::quote! { priority = 10 });
+        assert_eq!(options.take_int::<i32>("priority").unwrap(), Some(10));
+    }
+
+    #[test]
+    fn takes_negative_integers() {
+        let mut options = options(quote::quote! { priority = -1, other = -20 });
+        assert_eq!(options.take_int::<i32>("priority").unwrap(), Some(-1));
+        assert_eq!(options.take_int::<i64>("other").unwrap(), Some(-20));
+    }
+
+    #[test]
+    fn rejects_a_negative_unsigned_integer() {
+        let mut options = options(quote::quote! { limit = -1 });
+        assert!(options.take_int::<u32>("limit").is_err());
+    }
+
+    #[test]
+    fn rejects_negated_non_integers() {
+        assert!(syn::parse2::<RouteOptions>(quote::quote! { priority = -"1" }).is_err());
+    }
+
+    #[test]
+    fn takes_flags_and_lists() {
+        let mut options = options(quote::quote! { non_api, tags = [admin, "public"] });
+        assert!(options.take_flag("non_api").unwrap());
+        let tags: Vec<String> = options.take_str_list("tags").unwrap().iter().map(|tag| tag.value()).collect();
+        assert_eq!(tags, ["admin", "public"]);
+        assert!(options.ensure_empty(&[]).is_ok());
+    }
+}
@@ -1,12 +1,6 @@
-use proc_macro::TokenStream;
-use std::collections::HashMap;
-use proc_macro2::{Span, TokenStream as TokenStream2};
-use quote::{quote, ToTokens};
-use syn::{punctuated::Punctuated, LitStr, Path, Token};
-use syn::spanned::Spanned;
-use std::marker::PhantomData;
-use std::sync::Arc;
-use std::sync::Mutex;
+use proc_macro2::Span;
+use syn::Token;
+use crate::options::{RouteOption, RouteOptions};
 
 /*#[derive(Debug)]
 pub struct RouteOption{
@@ -22,29 +16,31 @@ impl RouteOption{
 #[derive(Debug)]
 pub struct RouteDef {
     pub path: String,
-    pub method: String,
-    pub options: HashMap<String,String>,
+    pub path_span: Span,
+    /// one or more methods, from `method = "get"`, `method = "get,head"` or `method = ["get", "head"]`
+    pub methods: Vec<String>,
+    /// middleware functions or layer expressions wrapping only this handler, outermost first
+    pub layers: Vec<syn::LitStr>,
+    /// the remaining options, taken by the route expansion
+    pub options: RouteOptions,
 }
 
 impl syn::parse::Parse for RouteDef {
     fn parse(input: syn::parse::ParseStream<'_>) -> syn::Result<Self> {
         // Parse the route path as a String
-        let path = input.parse::<syn::LitStr>().map_err(|mut e| {
+        let path_lit = input.parse::<syn::LitStr>().map_err(|mut e| {
             e.combine(syn::Error::new(
                 e.span(),
                 r#"Failed to parse route definition, expected #[route("<path>",method=\"get/post/...\")]"#,
             ));
             e
-        })?.value();// Convert LitStr to String
-
-        let possible_methods = vec!["get".to_string(), "post".to_string(), "put".to_string(),"delete".to_string(),"head".to_string(),"options".to_string(),"trace".to_string(),"patch".to_string()];
-
-        let mut method = "".to_string();
-        let mut options = HashMap::new();
+        })?;
+        let path = path_lit.value();// Convert LitStr to String
+        let path_span = path_lit.span();
 
         // Check for the next token
         let next_token: Result<Token![,], _> = input.parse();
-        match next_token {
+        let mut options = match next_token {
             Ok(_) => {
                 if input.cursor().literal().is_some() {
                     return Err(syn::Error::new(
@@ -52,47 +48,90 @@ impl syn::parse::Parse for RouteDef {
                         r#"Route options were expected, like method=\"get\", but a literal was given."#,
                     ));
                 }
-                while !input.is_empty() {
-                    let meta_name_value: syn::MetaNameValue = input.parse()?;
-                    // `value` is of type `syn::Expr`, so we need to match on it directly.
-                    if let syn::Expr::Lit(lit) = meta_name_value.value {
-                        if let syn::Lit::Str(lit_str) = lit.lit {
-                            let meta_name = meta_name_value.path.get_ident().unwrap().to_string();
-                            let meta_value = lit_str.value();
-                            if meta_name == "method".to_string() {
-                                method = meta_value;
-                            }else{
-                                options.insert(meta_name, meta_value);
-                            }
-                        }
-                    } else {
-                        return Err(syn::Error::new(
-                            meta_name_value.span(),
-                            "Expected a string literal for the option value.",
-                        ));
-                    }
-
-                    // Check for a comma to continue parsing more options
-                    if input.peek(Token![,]) {
-                        input.parse::<Token![,]>()?;
-                    } else {
-                        break; // Exit the loop if no more commas
-                    }
-                }
+                input.parse::<RouteOptions>()?
             },
-            Err(_) => {
-                // If there's no following token, we return an empty options array
-                return Ok(Self {
-                    path,
-                    method,
-                    options: HashMap::new(),
-                });
+            // If there's no following token, there are no options
+            Err(_) => RouteOptions::default(),
+        };
+
+        let methods = match options.take("method") {
+            Some(method_option) => {
+                let methods = parse_methods(&method_option)?;
+                validate_methods(&methods, &method_option)?;
+                methods
             },
+            None => vec![],
         };
-        Ok(Self { path, method, options })
+
+        let layers = options.take_str_list("layer")?;
+        for layer in layers.iter() {
+            crate::layer::parse_layer(&layer.value(), layer.span())?;
+        }
+
+        Ok(Self { path, path_span, methods, layers, options })
     }
 }
 
+/// the methods `axum::routing` has a function for
+pub const POSSIBLE_METHODS: [&str; 8] = ["get", "post", "put", "delete", "head", "options", "trace", "patch"];
+
+/// reject unknown, empty or repeated methods, pointing at the `method = ...` option
+fn validate_methods(methods: &[String], method_option: &RouteOption) -> syn::Result<()> {
+    if methods.iter().all(|method| method.is_empty()) {
+        return Err(syn::Error::new_spanned(
+            method_option,
+            format!("Expected at least one method, possible methods are {}.", POSSIBLE_METHODS.join(", ")),
+        ));
+    }
+    for (index, method) in methods.iter().enumerate() {
+        if method.is_empty() {
+            return Err(syn::Error::new_spanned(method_option, "Empty method, remove the extra comma."));
+        }
+        if !POSSIBLE_METHODS.contains(&method.as_str()) {
+            return Err(syn::Error::new_spanned(
+                method_option,
+                format!("Unknown method `{}`, did you mean `{}`?", method, closest_method(method)),
+            ));
+        }
+        if methods[..index].contains(method) {
+            return Err(syn::Error::new_spanned(method_option, format!("Method `{}` is given more than once.", method)));
+        }
+    }
+    Ok(())
+}
+
+/// the possible method with the smallest edit distance to the given one
+fn closest_method(method: &str) -> &'static str {
+    let method = method.to_lowercase();
+    POSSIBLE_METHODS.iter()
+        .min_by_key(|possible| edit_distance(&method, possible))
+        .copied()
+        .unwrap_or("get")
+}
+
+/// Levenshtein distance between two strings
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// parse the value of the `method` option, comma separated strings, idents or an array of them
+fn parse_methods(method_option: &RouteOption) -> syn::Result<Vec<String>> {
+    Ok(method_option.as_str_list()?
+        .iter()
+        .flat_map(|lit_str| lit_str.value().split(',').map(|m| m.trim().to_lowercase()).collect::<Vec<String>>())
+        .collect())
+}
+
 /*#[derive(Debug)]
 pub(crate) struct RouteDef {
     pub(crate) route_path: LitStr,
@@ -0,0 +1,192 @@
+use proc_macro2::Span;
+use syn::{FnArg, GenericArgument, ItemFn, Pat, PathArguments, Type};
+
+/// types a single path placeholder is extracted into, e.g. `Path<u32>`
+const SCALAR_TYPES: [&str; 17] = ["String", "str", "bool", "char", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize", "Uuid"];
+
+/// types taking any number of placeholders, e.g. `Path<HashMap<String, String>>`
+const MAP_TYPES: [&str; 3] = ["HashMap", "BTreeMap", "Vec"];
+
+/// The `{placeholder}` names of a route path like `/api/product/{id}/{action}`,
+/// a wildcard `{*rest}` is named `rest`. `{{` and `}}` are escaped braces.
+pub fn parse_placeholders(path: &str, span: Span) -> syn::Result<Vec<String>> {
+    let mut placeholders = vec![];
+    let mut chars = path.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+            },
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+            },
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some('{') | None => {
+                            return Err(syn::Error::new(span, format!("Unbalanced braces in route path `{}`.", path)));
+                        },
+                        Some(c) => name.push(c),
+                    }
+                }
+                let name = name.strip_prefix('*').unwrap_or(&name).to_string();
+                if name.is_empty() {
+                    return Err(syn::Error::new(span, format!("Empty placeholder in route path `{}`.", path)));
+                }
+                placeholders.push(name);
+            },
+            '}' => {
+                return Err(syn::Error::new(span, format!("Unbalanced braces in route path `{}`.", path)));
+            },
+            _ => {},
+        }
+    }
+
+    // axum 0.8 no longer treats `:id` and `*rest` segments as captures
+    if let Some(segment) = path.split('/').find(|segment| segment.starts_with(':') || segment.starts_with('*')) {
+        return Err(syn::Error::new(
+            span,
+            format!("`{}` is the axum 0.7 path syntax, write `{{{}}}` instead.", segment, segment.trim_start_matches(':')),
+        ));
+    }
+
+    Ok(placeholders)
+}
+
+/// The value of a `version = "v1"` option, a single path segment prepended to the route paths
+pub fn parse_version(version: &syn::LitStr) -> syn::Result<String> {
+    let value = version.value().trim().trim_matches('/').to_string();
+    if value.is_empty() || !value.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_')) {
+        return Err(syn::Error::new(
+            version.span(),
+            format!("Invalid version `{}`, expected a single path segment like \"v1\".", version.value()),
+        ));
+    }
+    Ok(value)
+}
+
+/// prepend the `#[handlers(prefix = ...)]` of the module to a route path,
+/// the route `/` of a module is the prefix itself
+pub fn join_prefix(prefix: &str, path: &str) -> String {
+    if prefix.is_empty() {
+        return path.to_string();
+    }
+    match path.trim_start_matches('/') {
+        "" => prefix.to_string(),
+        path => format!("{}/{}", prefix, path),
+    }
+}
+
+/// Compare the placeholders of the route path with the `Path<...>` argument of the handler:
+/// the arity of a tuple, a single scalar, and the names bound by a destructuring pattern.
+pub fn check_path_extractor(placeholders: &[String], input_fn: &ItemFn) -> syn::Result<()> {
+    for arg in input_fn.sig.inputs.iter() {
+        let FnArg::Typed(pat_type) = arg else {
+            continue;
+        };
+        let Some(inner_type) = path_extractor_type(&pat_type.ty) else {
+            continue;
+        };
+        if placeholders.is_empty() {
+            return Err(syn::Error::new_spanned(pat_type, "The handler takes a Path argument but the route path has no placeholders."));
+        }
+
+        // the number of values the extractor expects, if it can be told from the type
+        let expected = match inner_type {
+            Type::Tuple(tuple) => Some(tuple.elems.len()),
+            Type::Reference(_) => Some(1),
+            Type::Path(type_path) => {
+                let ident = type_path.path.segments.last().map(|segment| segment.ident.to_string()).unwrap_or_default();
+                if SCALAR_TYPES.contains(&ident.as_str()) {
+                    Some(1)
+                } else if MAP_TYPES.contains(&ident.as_str()) {
+                    return Ok(());
+                } else {
+                    None // a struct, only the pattern can tell its fields
+                }
+            },
+            _ => None,
+        };
+        if let Some(expected) = expected {
+            if expected != placeholders.len() {
+                return Err(syn::Error::new_spanned(
+                    pat_type,
+                    format!(
+                        "The route path has {} placeholder(s) {{{}}} but the Path argument takes {}.",
+                        placeholders.len(),
+                        placeholders.join("}, {"),
+                        expected,
+                    ),
+                ));
+            }
+        }
+
+        // names bound by `Path((id, action))` or `Path(Params { id, action })` must match the placeholders
+        let scalar = expected == Some(1) && !matches!(inner_type, Type::Tuple(_));
+        let (bound_names, exhaustive) = path_pattern_names(&pat_type.pat, scalar);
+        if expected.is_none() && exhaustive && !bound_names.is_empty() && bound_names.len() != placeholders.len() {
+            return Err(syn::Error::new_spanned(
+                &pat_type.pat,
+                format!("The route path has {} placeholder(s) but the Path pattern binds {}.", placeholders.len(), bound_names.len()),
+            ));
+        }
+        for (name, span) in bound_names {
+            if !placeholders.contains(&name) {
+                return Err(syn::Error::new(
+                    span,
+                    format!("`{}` is not a placeholder of the route path, expected one of {{{}}}.", name, placeholders.join("}, {")),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `T` of an argument typed `Path<T>`
+fn path_extractor_type(ty: &Type) -> Option<&Type> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != "Path" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    args.args.iter().find_map(|arg| match arg {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    })
+}
+
+/// the names bound inside `Path(...)`, skipping `_` and `_name` bindings,
+/// and whether the pattern binds every value (no `..` in a struct pattern).
+/// A plain `Path(id)` only names a placeholder when extracting a `scalar`, not a struct.
+fn path_pattern_names(pat: &Pat, scalar: bool) -> (Vec<(String, Span)>, bool) {
+    let Pat::TupleStruct(tuple_struct) = pat else {
+        return (vec![], false);
+    };
+    let Some(inner) = tuple_struct.elems.first() else {
+        return (vec![], false);
+    };
+    let (names, exhaustive): (Vec<(String, Span)>, bool) = match inner {
+        Pat::Tuple(tuple) => (tuple.elems.iter().filter_map(pat_ident_name).collect(), false),
+        Pat::Struct(pat_struct) => (pat_struct.fields.iter().filter_map(|field| match &field.member {
+            syn::Member::Named(ident) => Some((ident.to_string(), ident.span())),
+            syn::Member::Unnamed(_) => None,
+        }).collect(), pat_struct.rest.is_none()),
+        Pat::Ident(_) if scalar => (pat_ident_name(inner).into_iter().collect(), true),
+        _ => (vec![], false),
+    };
+    (names.into_iter().filter(|(name, _)| !name.starts_with('_')).collect(), exhaustive)
+}
+
+fn pat_ident_name(pat: &Pat) -> Option<(String, Span)> {
+    match pat {
+        Pat::Ident(pat_ident) => Some((pat_ident.ident.to_string(), pat_ident.ident.span())),
+        _ => None,
+    }
+}